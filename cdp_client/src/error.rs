@@ -0,0 +1,74 @@
+use crate::protocol::runtime::ExceptionDetails;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The debugger's `/json` endpoint couldn't be reached or read
+    Discovery(String),
+    /// The debugger didn't list a target matching the filter
+    NoTarget,
+    /// The websocket to the target failed or was closed
+    WebSocket(Box<tungstenite::Error>),
+    /// Not connected to a target
+    Disconnected,
+    /// No response arrived before the timeout
+    Timeout,
+    /// The browser rejected a request
+    Protocol {
+        code: i64,
+        message: String,
+    },
+    /// The evaluated javascript threw
+    Exception(Box<ExceptionDetails>),
+    /// A response didn't have the expected shape
+    UnexpectedResponse(Value),
+    Json(serde_json::Error),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Discovery(e) => write!(f, "unable to reach the debugger: {e}"),
+            Self::NoTarget => write!(f, "no matching tab is open"),
+            Self::WebSocket(e) => write!(f, "websocket error: {e}"),
+            Self::Disconnected => write!(f, "disconnected from the browser"),
+            Self::Timeout => write!(f, "timed out waiting for the browser"),
+            Self::Protocol { code, message } => write!(f, "protocol error {code}: {message}"),
+            Self::Exception(details) => write!(f, "javascript error: {details}"),
+            Self::UnexpectedResponse(value) => write!(f, "unexpected response: {value}"),
+            Self::Json(e) => write!(f, "invalid json: {e}"),
+            Self::Io(e) => write!(f, "io error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::WebSocket(e) => Some(e.as_ref()),
+            Self::Json(e) => Some(e),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(value: tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(value))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
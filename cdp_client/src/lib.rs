@@ -1,73 +1,298 @@
 use serde_json::{json, Value};
+use std::io::ErrorKind;
 use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
 use tungstenite::{connect, stream::MaybeTlsStream, Message, WebSocket};
-use ureq;
 
 use serde::{Deserialize, Serialize};
 
+mod error;
+pub mod protocol;
+mod target;
+pub use error::Error;
+use protocol::runtime::{AddBinding, Evaluate, RemoteObject};
+use protocol::{Command, Notification};
+pub use target::{targets, Target, TargetFilter};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolError {
+    pub code: i64,
+    pub message: String,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GCDResponse {
     pub id: u32,
+    #[serde(default)]
     pub result: Value,
+    pub error: Option<ProtocolError>,
 }
 
-pub type Root1 = Vec<Root2>;
-
+/// A message pushed by the browser without a matching request,
+/// e.g. `Runtime.bindingCalled`
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Root2 {
-    pub web_socket_debugger_url: String,
+pub struct Event {
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Event {
+    /// Reads the parameters of an event with `T`'s method
+    pub fn decode<T: Notification>(&self) -> Result<T, Error> {
+        if self.method != T::METHOD {
+            return Err(Error::UnexpectedResponse(json!(self)));
+        }
+        Ok(T::deserialize(&self.params)?)
+    }
+}
+
+fn would_block(error: &tungstenite::Error) -> bool {
+    matches!(error, tungstenite::Error::Io(e)
+        if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut))
+}
+
+/// A message read from the socket
+enum Incoming {
+    Response(GCDResponse),
+    /// An event or a message that isn't understood
+    Other,
+    /// No message was ready
+    Empty,
 }
 
+/// A connection to a browser tab.
+///
+/// Bindings and enabled domains survive page reloads and navigations. If the
+/// connection drops, e.g. because the browser restarted, the next request
+/// reconnects and sets them up again.
 pub struct Browser {
-    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    /// The debugger's `/json` endpoint
+    url: String,
+    filter: TargetFilter,
+    /// The target we connected to, which we follow across navigations
+    target_id: Option<String>,
+    socket: Option<WebSocket<MaybeTlsStream<TcpStream>>>,
     request_id: u32,
+    timeout: Duration,
+    /// Requests that are replayed after reconnecting
+    setup: Vec<(String, Value)>,
+    /// Channels that receive the events with a given method
+    subscribers: Vec<(String, Sender<Event>)>,
 }
 
 impl Browser {
-    pub fn new(url: &str) -> Result<Browser, Box<dyn std::error::Error>> {
-        let urls = ureq::get(url)
-            .call()
-            .expect("Failed to connect to debugger")
-            .into_json::<Root1>()?;
-        let url = &urls[0].web_socket_debugger_url;
-        let (socket, _response) = connect(url)?;
-        Ok(Browser {
-            socket,
+    /// Creates a client for the debugger at `url`, e.g. `http://localhost:9222/json`,
+    /// that connects to the target chosen by `filter` on the first request.
+    pub fn new(url: &str, filter: TargetFilter) -> Self {
+        Self {
+            url: url.to_owned(),
+            filter,
+            target_id: None,
+            socket: None,
             request_id: 0,
-        })
+            timeout: DEFAULT_TIMEOUT,
+            setup: Vec::new(),
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Creates a client for the debugger at `url` and connects to it
+    pub fn connect(url: &str, filter: TargetFilter) -> Result<Self, Error> {
+        let mut browser = Self::new(url, filter);
+        browser.reconnect()?;
+        Ok(browser)
+    }
+
+    /// Sets how long to wait for the response to a request
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    /// The id of the target we're following
+    pub fn target_id(&self) -> Option<&str> {
+        self.target_id.as_deref()
+    }
+
+    /// Connects to the target again, replaying bindings and enabled domains
+    pub fn reconnect(&mut self) -> Result<(), Error> {
+        self.socket = None;
+        let targets = targets(&self.url)?;
+        // stay on the same tab even if it navigated away from the filter's url
+        let target = self
+            .target_id
+            .as_ref()
+            .and_then(|id| {
+                targets
+                    .iter()
+                    .find(|t| t.id == *id && t.web_socket_debugger_url.is_some())
+            })
+            .or_else(|| self.filter.choose(&targets))
+            .ok_or(Error::NoTarget)?;
+        let url = target.web_socket_debugger_url.clone().unwrap_or_default();
+        let (socket, _response) = connect(url)?;
+        self.socket = Some(socket);
+        self.target_id = Some(target.id.clone());
+        for (method, params) in self.setup.clone() {
+            self.request(&method, params)?;
+        }
+        Ok(())
     }
 
-    /// Sends a request to the server
-    pub fn send(
-        &mut self,
-        method: &str,
-        params: Value,
-    ) -> Result<Value, Box<dyn std::error::Error>> {
+    fn stream(&mut self) -> Result<&mut TcpStream, Error> {
+        match self.socket.as_mut().map(WebSocket::get_mut) {
+            Some(MaybeTlsStream::Plain(stream)) => Ok(stream),
+            Some(_) => Err(Error::Io(ErrorKind::Unsupported.into())),
+            None => Err(Error::Disconnected),
+        }
+    }
+
+    /// Reads a message from the socket. Events are passed to subscribers.
+    fn read(&mut self) -> Result<Incoming, Error> {
+        let socket = self.socket.as_mut().ok_or(Error::Disconnected)?;
+        let msg = match socket.read_message() {
+            Ok(msg) => msg,
+            Err(e) if would_block(&e) => return Ok(Incoming::Empty),
+            Err(e) => {
+                self.socket = None;
+                return Err(e.into());
+            }
+        };
+        let Ok(text) = msg.to_text() else {
+            return Ok(Incoming::Other);
+        };
+        if let Ok(event) = serde_json::from_str::<Event>(text) {
+            self.dispatch(event);
+            return Ok(Incoming::Other);
+        }
+        Ok(serde_json::from_str(text).map_or(Incoming::Other, Incoming::Response))
+    }
+
+    fn dispatch(&mut self, event: Event) {
+        // drop subscribers whose receiver is gone
+        self.subscribers.retain(|(method, sender)| {
+            *method != event.method || sender.send(event.clone()).is_ok()
+        });
+    }
+
+    /// Sends a request and waits for the response with the same id
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, Error> {
+        self.request_id += 1;
+        let id = self.request_id;
         let data = json!({
-            "id": self.request_id,
+            "id": id,
             "method": method,
             "params": params,
         });
-        let msg = Message::Text(data.to_string());
-        self.socket.write_message(msg)?;
+        let socket = self.socket.as_mut().ok_or(Error::Disconnected)?;
+        if let Err(e) = socket.write_message(Message::Text(data.to_string())) {
+            self.socket = None;
+            return Err(e.into());
+        }
 
-        let msg = self.socket.read_message().expect("Error reading message");
-        let json: GCDResponse = serde_json::from_str(msg.to_text()?)?;
-        assert!(json.id == self.request_id);
+        // events and responses to requests that timed out may arrive first
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|d| !d.is_zero())
+                .ok_or(Error::Timeout)?;
+            self.stream()?.set_read_timeout(Some(remaining))?;
+            match self.read()? {
+                Incoming::Response(response) if response.id == id => {
+                    return match response.error {
+                        Some(e) => Err(Error::Protocol {
+                            code: e.code,
+                            message: e.message,
+                        }),
+                        None => Ok(response.result),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
 
-        self.request_id += 1;
-        Ok(json.result)
+    /// Sends a request to the server, reconnecting first if needed
+    pub fn send(&mut self, method: &str, params: Value) -> Result<Value, Error> {
+        if !self.is_connected() {
+            self.reconnect()?;
+        }
+        self.request(method, params)
     }
 
-    pub fn evaluate(&mut self, command: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let response = self.send("Runtime.evaluate", json!({ "expression": command }))?;
+    /// Sends a typed command, reconnecting first if needed
+    pub fn call<C: Command>(&mut self, command: &C) -> Result<C::Response, Error> {
+        let result = self.send(C::METHOD, serde_json::to_value(command)?)?;
+        C::Response::deserialize(&result).map_err(|_| Error::UnexpectedResponse(result))
+    }
 
-        if let Value::String(html) = &response["result"]["value"] {
-            Ok(html.to_owned())
+    /// Sends a command now and again after every reconnect
+    pub fn call_setup<C: Command>(&mut self, command: &C) -> Result<(), Error> {
+        let (method, params) = (C::METHOD, serde_json::to_value(command)?);
+        self.setup.push((method.to_owned(), params.clone()));
+        if self.is_connected() {
+            self.request(method, params)?;
         } else {
-            panic!("Failed to find game log.");
+            self.reconnect()?;
+        }
+        Ok(())
+    }
+
+    /// Returns a channel that receives every event with the given method
+    pub fn subscribe(&mut self, method: &str) -> Receiver<Event> {
+        let (sender, receiver) = channel();
+        self.subscribers.push((method.to_owned(), sender));
+        receiver
+    }
+
+    /// Passes the events received so far to subscribers without blocking
+    pub fn poll(&mut self) -> Result<(), Error> {
+        self.stream()?.set_nonblocking(true)?;
+        let result = loop {
+            match self.read() {
+                Ok(Incoming::Empty) => break Ok(()),
+                Ok(_) => {}
+                Err(e) => break Err(e),
+            }
+        };
+        if let Ok(stream) = self.stream() {
+            stream.set_nonblocking(false)?;
+        }
+        result
+    }
+
+    /// Exposes a function with the given name on the page's global object.
+    /// Calling it produces a `Runtime.bindingCalled` event with the argument as its payload.
+    pub fn add_binding(&mut self, name: &str) -> Result<(), Error> {
+        self.call_setup(&AddBinding {
+            name: name.to_owned(),
+        })
+    }
+
+    /// Evaluates an expression, turning a thrown exception into an error
+    pub fn evaluate_object(&mut self, evaluate: &Evaluate) -> Result<RemoteObject, Error> {
+        let response = self.call(evaluate)?;
+        match response.exception_details {
+            Some(details) => Err(Error::Exception(Box::new(details))),
+            None => Ok(response.result),
+        }
+    }
+
+    /// Evaluates an expression that returns a string
+    pub fn evaluate(&mut self, expression: &str) -> Result<String, Error> {
+        let result = self.evaluate_object(&Evaluate::new(expression))?;
+        match result.value {
+            Some(Value::String(value)) => Ok(value),
+            _ => Err(Error::UnexpectedResponse(json!(result))),
         }
     }
 }
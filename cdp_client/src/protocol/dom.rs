@@ -0,0 +1,89 @@
+//! The `DOM` domain: reading the page's elements
+use super::command;
+use serde::{Deserialize, Serialize};
+
+pub type NodeId = i64;
+
+/// An element, text or document node
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Node {
+    pub node_id: NodeId,
+    pub node_type: i64,
+    pub node_name: String,
+    #[serde(default)]
+    pub node_value: String,
+    /// Only the children that were requested
+    #[serde(default)]
+    pub children: Vec<Node>,
+    /// Alternating names and values
+    #[serde(default)]
+    pub attributes: Vec<String>,
+}
+
+/// Returns the root of the document. Node ids are only valid until the
+/// document changes.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetDocument {
+    /// How deep to return children, -1 for the whole tree
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<i64>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetDocumentResponse {
+    pub root: Node,
+}
+
+command!(GetDocument, "DOM.getDocument", GetDocumentResponse);
+
+/// Finds the first descendant of a node matching a css selector
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySelector {
+    pub node_id: NodeId,
+    pub selector: String,
+}
+
+/// The node id is 0 when nothing matched
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySelectorResponse {
+    pub node_id: NodeId,
+}
+
+command!(QuerySelector, "DOM.querySelector", QuerySelectorResponse);
+
+/// Finds every descendant of a node matching a css selector
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySelectorAll {
+    pub node_id: NodeId,
+    pub selector: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySelectorAllResponse {
+    pub node_ids: Vec<NodeId>,
+}
+
+command!(
+    QuerySelectorAll,
+    "DOM.querySelectorAll",
+    QuerySelectorAllResponse
+);
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetOuterHtml {
+    pub node_id: NodeId,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetOuterHtmlResponse {
+    #[serde(rename = "outerHTML")]
+    pub outer_html: String,
+}
+
+command!(GetOuterHtml, "DOM.getOuterHTML", GetOuterHtmlResponse);
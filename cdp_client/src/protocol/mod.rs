@@ -0,0 +1,51 @@
+//! Typed requests, responses and events for the parts of the protocol we use.
+//!
+//! Each domain has its own module, named after the domain. Commands are sent
+//! with [`Browser::call`](crate::Browser::call) and events are read with
+//! [`Event::decode`](crate::Event::decode).
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub mod dom;
+pub mod page;
+pub mod runtime;
+pub mod target;
+
+/// A request and the type of its result
+pub trait Command: Serialize {
+    /// The protocol method, e.g. `Runtime.evaluate`
+    const METHOD: &'static str;
+    type Response: DeserializeOwned;
+}
+
+/// The parameters of an event pushed by the browser
+pub trait Notification: DeserializeOwned {
+    /// The protocol method, e.g. `Runtime.bindingCalled`
+    const METHOD: &'static str;
+}
+
+/// The result of commands that don't return anything
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Empty {}
+
+/// Implements [`Command`] for a request
+macro_rules! command {
+    ($request:ty, $method:literal, $response:ty) => {
+        impl $crate::protocol::Command for $request {
+            const METHOD: &'static str = $method;
+            type Response = $response;
+        }
+    };
+}
+
+/// Implements [`Notification`] for an event's parameters
+macro_rules! notification {
+    ($params:ty, $method:literal) => {
+        impl $crate::protocol::Notification for $params {
+            const METHOD: &'static str = $method;
+        }
+    };
+}
+
+use command;
+use notification;
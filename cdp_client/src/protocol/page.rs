@@ -0,0 +1,52 @@
+//! The `Page` domain: navigating the tab
+use super::{command, notification, Empty};
+use serde::{Deserialize, Serialize};
+
+/// Starts reporting page events such as `FrameNavigated`
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Enable {}
+
+command!(Enable, "Page.enable", Empty);
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Navigate {
+    pub url: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigateResponse {
+    pub frame_id: String,
+    /// Set when the navigation failed
+    pub error_text: Option<String>,
+}
+
+command!(Navigate, "Page.navigate", NavigateResponse);
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reload {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub ignore_cache: bool,
+}
+
+command!(Reload, "Page.reload", Empty);
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BringToFront {}
+
+command!(BringToFront, "Page.bringToFront", Empty);
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub id: String,
+    pub url: String,
+}
+
+/// A frame finished navigating, e.g. to a new game
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameNavigated {
+    pub frame: Frame,
+}
+
+notification!(FrameNavigated, "Page.frameNavigated");
@@ -0,0 +1,106 @@
+//! The `Runtime` domain: evaluating javascript and bindings
+use super::{command, notification, Empty};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Evaluates an expression in the page's global scope
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Evaluate {
+    pub expression: String,
+    /// Return the result as JSON instead of a reference to the object
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub return_by_value: bool,
+    /// Wait for the result if it's a promise
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub await_promise: bool,
+}
+
+impl Evaluate {
+    /// Evaluates `expression` and returns the result by value
+    pub fn new(expression: &str) -> Self {
+        Self {
+            expression: expression.to_owned(),
+            return_by_value: true,
+            await_promise: false,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateResponse {
+    pub result: RemoteObject,
+    /// Set when the expression threw
+    pub exception_details: Option<ExceptionDetails>,
+}
+
+command!(Evaluate, "Runtime.evaluate", EvaluateResponse);
+
+/// A javascript value
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteObject {
+    /// e.g. `string`, `object` or `undefined`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// e.g. `null`, `array` or `node` for objects
+    pub subtype: Option<String>,
+    /// The value itself, for primitives and objects returned by value
+    pub value: Option<Value>,
+    pub description: Option<String>,
+    /// Refers to the object in the page when it isn't returned by value
+    pub object_id: Option<String>,
+}
+
+/// Why an evaluation threw
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExceptionDetails {
+    pub exception_id: i64,
+    /// e.g. `Uncaught`
+    pub text: String,
+    pub line_number: i64,
+    pub column_number: i64,
+    /// The thrown value
+    pub exception: Option<RemoteObject>,
+}
+
+impl fmt::Display for ExceptionDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the description holds the message and stack of thrown errors
+        match self.exception.as_ref().and_then(|e| e.description.as_ref()) {
+            Some(description) => write!(f, "{} {description}", self.text),
+            None => write!(f, "{}", self.text),
+        }
+    }
+}
+
+/// Exposes a function on the page's global object that produces
+/// `BindingCalled` events
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddBinding {
+    pub name: String,
+}
+
+command!(AddBinding, "Runtime.addBinding", Empty);
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoveBinding {
+    pub name: String,
+}
+
+command!(RemoveBinding, "Runtime.removeBinding", Empty);
+
+/// A binding was called from the page
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingCalled {
+    pub name: String,
+    /// The argument passed to the binding
+    pub payload: String,
+    pub execution_context_id: i64,
+}
+
+notification!(BindingCalled, "Runtime.bindingCalled");
@@ -0,0 +1,36 @@
+//! The `Target` domain: the browser's tabs and workers
+use super::{command, Empty};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetInfo {
+    pub target_id: String,
+    /// e.g. `page` or `service_worker`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub url: String,
+    /// Whether a client is attached
+    pub attached: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetTargets {}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetTargetsResponse {
+    pub target_infos: Vec<TargetInfo>,
+}
+
+command!(GetTargets, "Target.getTargets", GetTargetsResponse);
+
+/// Brings a tab to the front
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivateTarget {
+    pub target_id: String,
+}
+
+command!(ActivateTarget, "Target.activateTarget", Empty);
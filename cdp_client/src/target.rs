@@ -0,0 +1,94 @@
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// A tab, worker or extension page listed by the debugger's `/json` endpoint
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    /// Missing when another client is already attached to the target
+    pub web_socket_debugger_url: Option<String>,
+}
+
+/// Lists the targets of the debugger at `url`, e.g. `http://localhost:9222/json`
+pub fn targets(url: &str) -> Result<Vec<Target>, Error> {
+    Ok(ureq::get(url)
+        .call()
+        .map_err(|e| Error::Discovery(e.to_string()))?
+        .into_json()?)
+}
+
+/// Chooses which target to connect to
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct TargetFilter {
+    /// Only targets of this type, e.g. `page`
+    pub kind: Option<String>,
+    /// Only targets whose url contains this
+    pub url: Option<String>,
+    /// Which of the matching targets to pick, in the debugger's order
+    pub index: usize,
+}
+
+impl TargetFilter {
+    /// Matches pages whose url contains `url`
+    pub fn page(url: &str) -> Self {
+        Self {
+            kind: Some("page".to_owned()),
+            url: Some(url.to_owned()),
+            index: 0,
+        }
+    }
+
+    pub fn matches(&self, target: &Target) -> bool {
+        self.kind.as_ref().is_none_or(|kind| *kind == target.kind)
+            && self.url.as_ref().is_none_or(|url| target.url.contains(url))
+            && target.web_socket_debugger_url.is_some()
+    }
+
+    /// Returns the targets that match, ignoring `index`
+    pub fn candidates<'a>(&self, targets: &'a [Target]) -> Vec<&'a Target> {
+        targets.iter().filter(|t| self.matches(t)).collect()
+    }
+
+    pub fn choose<'a>(&self, targets: &'a [Target]) -> Option<&'a Target> {
+        self.candidates(targets).get(self.index).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(id: &str, kind: &str, url: &str) -> Target {
+        Target {
+            id: id.to_owned(),
+            kind: kind.to_owned(),
+            url: url.to_owned(),
+            web_socket_debugger_url: Some(format!("ws://localhost:9222/devtools/page/{id}")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_choose() {
+        let targets = [
+            target("1", "service_worker", "https://colonist.io/sw.js"),
+            target("2", "page", "chrome-extension://abc/popup.html"),
+            target("3", "page", "https://colonist.io/#Qk3a"),
+            target("4", "page", "https://colonist.io/#Zx9p"),
+        ];
+        let mut filter = TargetFilter::page("colonist.io");
+        assert_eq!(filter.candidates(&targets).len(), 2);
+        assert_eq!(filter.choose(&targets).unwrap().id, "3");
+        filter.index = 1;
+        assert_eq!(filter.choose(&targets).unwrap().id, "4");
+        filter.index = 2;
+        assert_eq!(filter.choose(&targets), None);
+    }
+}
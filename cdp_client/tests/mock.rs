@@ -0,0 +1,144 @@
+use cdp_client::protocol::runtime::{BindingCalled, Evaluate};
+use cdp_client::{Browser, Error, TargetFilter};
+use cdp_mock::{MockBrowser, PAGE_ID};
+use serde_json::json;
+use std::time::Duration;
+
+const LOG: &str = r#"<div class="message_post">one</div><div class="message_post">two</div>"#;
+
+fn connect(mock: &MockBrowser) -> Browser {
+    Browser::connect(&mock.url(), TargetFilter::page("colonist.io")).unwrap()
+}
+
+#[test]
+fn test_choose_page() {
+    let mock = MockBrowser::start(LOG);
+    let browser = connect(&mock);
+    assert_eq!(browser.target_id(), Some(PAGE_ID));
+
+    let filter = TargetFilter {
+        index: 1,
+        ..TargetFilter::page("colonist.io")
+    };
+    assert!(matches!(
+        Browser::connect(&mock.url(), filter),
+        Err(Error::NoTarget)
+    ));
+}
+
+#[test]
+fn test_evaluate() {
+    let mock = MockBrowser::start(LOG);
+    mock.on_evaluate(|expression, _| json!(expression.to_uppercase()));
+    let mut browser = connect(&mock);
+    assert_eq!(
+        browser.evaluate("document.title").unwrap(),
+        "DOCUMENT.TITLE"
+    );
+
+    mock.on_evaluate(|_, _| json!(null));
+    assert!(matches!(
+        browser.evaluate("document.title"),
+        Err(Error::UnexpectedResponse(_))
+    ));
+}
+
+#[test]
+fn test_exception() {
+    let mock = MockBrowser::start(LOG);
+    mock.on_evaluate(|_, page| {
+        page.throw("log is not defined");
+        json!(null)
+    });
+    let mut browser = connect(&mock);
+    let Err(Error::Exception(details)) = browser.evaluate("log.innerHTML") else {
+        panic!("the exception wasn't reported");
+    };
+    assert_eq!(details.to_string(), "Uncaught Error: log is not defined");
+}
+
+#[test]
+fn test_evaluate_object() {
+    let mock = MockBrowser::start(LOG);
+    mock.on_evaluate(|_, _| json!([1, 2]));
+    let mut browser = connect(&mock);
+    let result = browser.evaluate_object(&Evaluate::new("[1, 2]")).unwrap();
+    assert_eq!(result.kind, "object");
+    assert_eq!(result.value, Some(json!([1, 2])));
+
+    mock.on_evaluate(|_, _| json!(null));
+    let result = browser.call(&Evaluate::new("null")).unwrap().result;
+    assert_eq!(result.subtype.as_deref(), Some("null"));
+}
+
+#[test]
+fn test_binding_events() {
+    let mock = MockBrowser::start(LOG);
+    mock.on_evaluate(|_, page| {
+        page.observe("onMessage");
+        json!("")
+    });
+    let mut browser = connect(&mock);
+    let messages = browser.subscribe("Runtime.bindingCalled");
+    browser.add_binding("onMessage").unwrap();
+    browser.evaluate("observe()").unwrap();
+
+    mock.advance(1);
+    // events emitted while handling a request arrive before its response
+    mock.on_evaluate(|_, page| {
+        page.emit(
+            "Runtime.bindingCalled",
+            json!({ "name": "other", "payload": "", "executionContextId": 1 }),
+        );
+        json!("")
+    });
+    browser.evaluate("emit()").unwrap();
+    let names: Vec<_> = messages
+        .try_iter()
+        .map(|event| event.decode::<BindingCalled>().unwrap().name)
+        .collect();
+    assert_eq!(names, ["onMessage", "other"]);
+
+    mock.advance(1);
+    std::thread::sleep(Duration::from_millis(50));
+    browser.poll().unwrap();
+    let payloads: Vec<_> = messages
+        .try_iter()
+        .map(|event| event.decode::<BindingCalled>().unwrap().payload)
+        .collect();
+    assert_eq!(payloads, [r#"<div class="message_post">two</div>"#]);
+}
+
+#[test]
+fn test_reconnect() {
+    let mock = MockBrowser::start(LOG);
+    let mut browser = connect(&mock);
+    browser.add_binding("onMessage").unwrap();
+
+    mock.disconnect_all();
+    std::thread::sleep(Duration::from_millis(50));
+    // the first request notices the dropped connection
+    let _ = browser.evaluate("1");
+    assert!(browser.evaluate("1").is_ok());
+    assert!(browser.is_connected());
+    assert_eq!(browser.target_id(), Some(PAGE_ID));
+    // the binding was added again on the new connection
+    assert!(mock.with_page(|page| page.has_binding("onMessage")));
+}
+
+#[test]
+fn test_timeout() {
+    let mock = MockBrowser::start(LOG);
+    mock.on_evaluate(|_, _| {
+        std::thread::sleep(Duration::from_millis(200));
+        json!("late")
+    });
+    let mut browser = connect(&mock);
+    browser.set_timeout(Duration::from_millis(50));
+    assert!(matches!(browser.evaluate("1"), Err(Error::Timeout)));
+
+    // the late response is skipped
+    mock.on_evaluate(|_, _| json!("on time"));
+    browser.set_timeout(Duration::from_secs(1));
+    assert_eq!(browser.evaluate("1").unwrap(), "on time");
+}
@@ -0,0 +1,338 @@
+//! A fake Chrome DevTools endpoint for tests.
+//!
+//! Serves a `/json` target list and a websocket that answers requests and
+//! pushes events. It can't run javascript, so `Runtime.evaluate` is answered by
+//! a handler the test provides. The page holds a recorded game log that is
+//! revealed a few messages at a time, so tests can replay a game incrementally.
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::{accept, Message};
+
+/// The id of the page target that serves the game
+pub const PAGE_ID: &str = "E2A3B1C4D5";
+pub const PAGE_URL: &str = "https://colonist.io/#Qk3a";
+
+/// The state of the page as seen through the protocol
+#[derive(Default)]
+pub struct Page {
+    /// Every message of the recorded log
+    messages: Vec<String>,
+    /// How many messages are shown in the log so far
+    shown: usize,
+    /// Bindings added with `Runtime.addBinding`
+    bindings: Vec<String>,
+    /// Bindings called with each new log message
+    observers: Vec<String>,
+    /// Events to push to the clients
+    outbox: Vec<Value>,
+    /// Set by the evaluate handler to make the expression throw
+    exception: Option<String>,
+}
+
+impl Page {
+    /// The inner html of the log as shown so far
+    pub fn log_html(&self) -> String {
+        self.messages[..self.shown].concat()
+    }
+
+    /// Calls `binding` with the outer html of every message added from now on,
+    /// like a `MutationObserver` on the log would
+    pub fn observe(&mut self, binding: &str) {
+        self.observers.push(binding.to_owned());
+    }
+
+    pub fn has_binding(&self, name: &str) -> bool {
+        self.bindings.iter().any(|b| b == name)
+    }
+
+    /// Pushes an event to every connected client
+    pub fn emit(&mut self, method: &str, params: Value) {
+        self.outbox
+            .push(json!({ "method": method, "params": params }));
+    }
+
+    /// Makes the expression being evaluated throw an error with `message`
+    pub fn throw(&mut self, message: &str) {
+        self.exception = Some(message.to_owned());
+    }
+
+    /// Shows up to `n` more messages, returning how many were added
+    fn advance(&mut self, n: usize) -> usize {
+        let end = (self.shown + n).min(self.messages.len());
+        for i in self.shown..end {
+            for binding in self.observers.clone() {
+                if self.has_binding(&binding) {
+                    let payload = self.messages[i].clone();
+                    self.emit(
+                        "Runtime.bindingCalled",
+                        json!({ "name": binding, "payload": payload, "executionContextId": 1 }),
+                    );
+                }
+            }
+        }
+        let added = end - self.shown;
+        self.shown = end;
+        added
+    }
+}
+
+/// Splits a log into its `.message_post` elements
+fn split_messages(html: &str) -> Vec<String> {
+    const START: &str = "<div class=\"message_post\"";
+    let mut messages = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find(START) {
+        rest = &rest[start..];
+        let end = rest[START.len()..]
+            .find(START)
+            .map_or(rest.len(), |i| i + START.len());
+        messages.push(rest[..end].trim_end().trim_end_matches('\'').to_owned());
+        rest = &rest[end..];
+    }
+    messages
+}
+
+/// Describes a value returned by an evaluation, like `returnByValue` does
+fn remote_object(value: Value) -> Value {
+    let kind = match value {
+        Value::Null | Value::Array(_) | Value::Object(_) => "object",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+    };
+    match value {
+        Value::Null => json!({ "type": kind, "subtype": "null", "value": null }),
+        value => json!({ "type": kind, "value": value }),
+    }
+}
+
+type Handler = Box<dyn FnMut(&str, &mut Page) -> Value + Send>;
+
+struct Shared {
+    page: Mutex<Page>,
+    evaluate: Mutex<Handler>,
+    /// Outgoing messages for each connected client
+    clients: Mutex<Vec<Sender<String>>>,
+}
+
+impl Shared {
+    /// Sends the events in the page's outbox to every client
+    fn flush(&self) {
+        let events = std::mem::take(&mut self.page.lock().unwrap().outbox);
+        let mut clients = self.clients.lock().unwrap();
+        for event in events {
+            clients.retain(|client| client.send(event.to_string()).is_ok());
+        }
+    }
+
+    fn handle(&self, request: &Value) -> Value {
+        let params = &request["params"];
+        let result = match request["method"].as_str().unwrap_or_default() {
+            "Runtime.evaluate" => {
+                let expression = params["expression"].as_str().unwrap_or_default();
+                let mut page = self.page.lock().unwrap();
+                let value = (self.evaluate.lock().unwrap())(expression, &mut page);
+                match page.exception.take() {
+                    Some(message) => json!({
+                        "result": { "type": "object", "subtype": "error", "description": format!("Error: {message}") },
+                        "exceptionDetails": {
+                            "exceptionId": 1,
+                            "text": "Uncaught",
+                            "lineNumber": 0,
+                            "columnNumber": 0,
+                            "exception": { "type": "object", "subtype": "error", "description": format!("Error: {message}") },
+                        },
+                    }),
+                    None => json!({ "result": remote_object(value) }),
+                }
+            }
+            "Runtime.addBinding" => {
+                let name = params["name"].as_str().unwrap_or_default();
+                self.page.lock().unwrap().bindings.push(name.to_owned());
+                json!({})
+            }
+            _ => json!({}),
+        };
+        json!({ "id": request["id"], "result": result })
+    }
+}
+
+/// A fake browser with a single colonist.io tab
+pub struct MockBrowser {
+    port: u16,
+    shared: Arc<Shared>,
+}
+
+impl MockBrowser {
+    /// Starts serving a page whose game log will show the messages of `log`.
+    /// `Runtime.evaluate` returns an empty string until `on_evaluate` is called.
+    pub fn start(log: &str) -> Self {
+        let shared = Arc::new(Shared {
+            page: Mutex::new(Page {
+                messages: split_messages(log),
+                ..Page::default()
+            }),
+            evaluate: Mutex::new(Box::new(|_, _| json!(""))),
+            clients: Mutex::new(Vec::new()),
+        });
+
+        let http = TcpListener::bind("127.0.0.1:0").unwrap();
+        let websocket = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = http.local_addr().unwrap().port();
+        let ws_port = websocket.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            for stream in http.incoming().flatten() {
+                serve_targets(stream, ws_port);
+            }
+        });
+        let server = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in websocket.incoming().flatten() {
+                let server = Arc::clone(&server);
+                thread::spawn(move || serve_websocket(stream, &server));
+            }
+        });
+        Self { port, shared }
+    }
+
+    /// The host and port of the debugger, e.g. for `--debugger`
+    pub fn debugger(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+
+    /// The url of the target list
+    pub fn url(&self) -> String {
+        format!("http://{}/json", self.debugger())
+    }
+
+    /// Answers `Runtime.evaluate` requests with the value returned by `handler`
+    pub fn on_evaluate(&self, handler: impl FnMut(&str, &mut Page) -> Value + Send + 'static) {
+        *self.shared.evaluate.lock().unwrap() = Box::new(handler);
+    }
+
+    /// Runs `f` on the page and pushes the events it emits
+    pub fn with_page<T>(&self, f: impl FnOnce(&mut Page) -> T) -> T {
+        let result = f(&mut self.shared.page.lock().unwrap());
+        self.shared.flush();
+        result
+    }
+
+    /// Shows up to `n` more messages of the log, returning how many were added
+    pub fn advance(&self, n: usize) -> usize {
+        self.with_page(|page| page.advance(n))
+    }
+
+    /// Whether every message of the log is shown
+    pub fn finished(&self) -> bool {
+        self.with_page(|page| page.shown == page.messages.len())
+    }
+
+    /// Closes every websocket connection and forgets the bindings, like a
+    /// browser restart would
+    pub fn disconnect_all(&self) {
+        self.shared.clients.lock().unwrap().clear();
+        self.shared.page.lock().unwrap().bindings.clear();
+    }
+}
+
+/// Answers a request for the target list. Besides the game there's a service
+/// worker and an extension page, which clients should skip.
+fn serve_targets(stream: TcpStream, ws_port: u16) {
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    // skip the request head
+    while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+        line.clear();
+    }
+    let targets = json!([
+        {
+            "id": "SW1",
+            "type": "service_worker",
+            "title": "Service Worker",
+            "url": "https://colonist.io/sw.js",
+            "webSocketDebuggerUrl": format!("ws://127.0.0.1:{ws_port}/devtools/page/SW1"),
+        },
+        {
+            "id": "EXT1",
+            "type": "page",
+            "title": "Extension",
+            "url": "chrome-extension://abc/popup.html",
+            "webSocketDebuggerUrl": format!("ws://127.0.0.1:{ws_port}/devtools/page/EXT1"),
+        },
+        {
+            "id": PAGE_ID,
+            "type": "page",
+            "title": "Colonist: Settlers of Catan",
+            "url": PAGE_URL,
+            "webSocketDebuggerUrl": format!("ws://127.0.0.1:{ws_port}/devtools/page/{PAGE_ID}"),
+        },
+    ])
+    .to_string();
+    let _ = write!(
+        &stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{targets}",
+        targets.len()
+    );
+}
+
+fn serve_websocket(stream: TcpStream, shared: &Shared) {
+    let Ok(mut socket) = accept(stream) else {
+        return;
+    };
+    // wake up regularly to push events
+    socket
+        .get_mut()
+        .set_read_timeout(Some(Duration::from_millis(5)))
+        .unwrap();
+    let (sender, outgoing): (Sender<String>, Receiver<String>) = channel();
+    shared.clients.lock().unwrap().push(sender);
+
+    loop {
+        // pushed events, until the server drops us
+        loop {
+            match outgoing.try_recv() {
+                Ok(event) => {
+                    if socket.write_message(Message::Text(event)).is_err() {
+                        return;
+                    }
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    let _ = socket.close(None);
+                    let _ = socket.write_pending();
+                    return;
+                }
+            }
+        }
+        match socket.read_message() {
+            Ok(Message::Text(text)) => {
+                let Ok(request) = serde_json::from_str::<Value>(&text) else {
+                    continue;
+                };
+                let response = shared.handle(&request);
+                // events emitted while handling the request arrive before its response
+                shared.flush();
+                while let Ok(event) = outgoing.try_recv() {
+                    let _ = socket.write_message(Message::Text(event));
+                }
+                if socket
+                    .write_message(Message::Text(response.to_string()))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            Ok(Message::Close(_)) => return,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => return,
+        }
+    }
+}
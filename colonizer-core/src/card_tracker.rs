@@ -0,0 +1,429 @@
+//! Keeps every combination of hands the game so far allows
+use crate::{
+    hand::{possible_hands, Hand},
+    resource::{Resource, N_RESOURCES},
+};
+use std::collections::HashMap;
+
+const fn gcd(a: u32, b: u32) -> u32 {
+    // Use Stein's algorithm
+    let mut m = a;
+    let mut n = b;
+    if m == 0 || n == 0 {
+        return m | n;
+    }
+
+    // find common factors of 2
+    let shift = (m | n).trailing_zeros();
+
+    // divide n and m by 2 until odd
+    m >>= m.trailing_zeros();
+    n >>= n.trailing_zeros();
+
+    while m != n {
+        if m > n {
+            m -= n;
+            m >>= m.trailing_zeros();
+        } else {
+            n -= m;
+            n >>= n.trailing_zeros();
+        }
+    }
+    m << shift
+}
+
+/// Divides the state counts by their GCD
+fn normalize(results: HashMap<State, u32>) -> Vec<(State, u32)> {
+    let gcd = results.values().fold(0, |a, b| gcd(a, *b));
+    results.into_iter().map(|(a, b)| (a, b / gcd)).collect()
+}
+
+/// The most players a game can have
+pub const MAX_PLAYERS: usize = 6;
+/// The hands of the players, by seat
+pub type State = [Hand; MAX_PLAYERS];
+
+/// Tracks the possible hands of the players, which are uncertain after robs
+/// between other players. Players are referred to by seat.
+pub struct CardTracker {
+    /// A list of all states and their frequency
+    states: Vec<(State, u32)>,
+}
+
+impl Default for CardTracker {
+    fn default() -> Self {
+        Self {
+            states: vec![(State::default(), 1)],
+        }
+    }
+}
+
+impl CardTracker {
+    /// Returns the number of states. This is the number of possible
+    /// combinations of cards that are consistent with the game log.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Whether no combination of cards is consistent with the game log, which
+    /// means an event was misread
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Removes states where player does not have that many cards.
+    /// Returns `false` and leaves the states untouched if no state has them.
+    pub fn know_has(&mut self, player: usize, cards: Hand) -> bool {
+        let has = |state: &State| {
+            state[player]
+                .values()
+                .zip(cards.values())
+                .all(|(a, b)| a >= b)
+        };
+        if !self.states.iter().any(|(state, _)| has(state)) {
+            return false;
+        }
+        self.states.retain(|(state, _)| has(state));
+        true
+    }
+
+    /// Adds a `Hand` of cards to every state for a player
+    pub fn add(&mut self, player: usize, cards: Hand) {
+        for (state, _) in &mut self.states {
+            for (card, count) in cards {
+                state[player][card] += count;
+            }
+        }
+    }
+
+    /// Removes a `Hand` of cards from every state for a player.
+    /// Returns `false` if no state has them, in which case the player is left
+    /// with none of those resources, as the cards were missed.
+    pub fn remove(&mut self, player: usize, cards: Hand) -> bool {
+        if self.know_has(player, cards) {
+            for (state, _) in &mut self.states {
+                for (card, count) in cards {
+                    state[player][card] -= count;
+                }
+            }
+            return true;
+        }
+        let mut results = HashMap::new();
+        for &(state, count) in &self.states {
+            let mut s_new = state;
+            for (card, num) in cards {
+                s_new[player][card] = s_new[player][card].saturating_sub(num);
+            }
+            *results.entry(s_new).or_insert(0) += count;
+        }
+        self.states = normalize(results);
+        false
+    }
+
+    /// Handles a rob involving two players where we don't know what card was
+    /// taken. Returns `false` and leaves the states untouched if the victim
+    /// has no cards.
+    pub fn rob(&mut self, robber: usize, victim: usize) -> bool {
+        let mut results = HashMap::new();
+        for (state, count) in &self.states {
+            for (card, num) in state[victim].into_iter().filter(|(_, c)| *c > 0) {
+                let mut s_new = *state;
+                s_new[robber][card] += 1;
+                s_new[victim][card] -= 1;
+                *results.entry(s_new).or_insert(0) += u32::from(num) * count;
+            }
+        }
+        if results.is_empty() {
+            return false;
+        }
+        self.states = normalize(results);
+        true
+    }
+
+    /// Handles a rob where we know the card that was taken, but not which of
+    /// `victims` it was taken from. Each victim is weighted by the chance of
+    /// drawing the card from their hand. Returns `false` and leaves the states
+    /// untouched if none of them can hold the card.
+    pub fn rob_any(&mut self, robber: usize, card: Resource, victims: &[usize]) -> bool {
+        // the chances are `num / size`, so scale them by the sizes' LCM to keep them whole
+        let scale = self
+            .states
+            .iter()
+            .flat_map(|(state, _)| victims.iter().map(move |&v| u32::from(state[v].size())))
+            .filter(|&size| size > 0)
+            .fold(1, |a, b| a / gcd(a, b) * b);
+        let mut results = HashMap::new();
+        for (state, count) in &self.states {
+            for &victim in victims.iter().filter(|&&v| state[v][card] > 0) {
+                let mut s_new = *state;
+                s_new[robber][card] += 1;
+                s_new[victim][card] -= 1;
+                let chance =
+                    u32::from(state[victim][card]) * scale / u32::from(state[victim].size());
+                *results.entry(s_new).or_insert(0) += chance * count;
+            }
+        }
+        if results.is_empty() {
+            return false;
+        }
+        self.states = normalize(results);
+        true
+    }
+
+    /// Removes states where the player doesn't hold exactly `size` cards.
+    /// Returns `false` and leaves the states untouched if no state matches.
+    pub fn know_size(&mut self, player: usize, size: u8) -> bool {
+        if !self
+            .states
+            .iter()
+            .any(|(state, _)| state[player].size() == size)
+        {
+            return false;
+        }
+        self.states
+            .retain(|(state, _)| state[player].size() == size);
+        true
+    }
+
+    /// Removes states where the player's hand isn't exactly `cards`.
+    /// If no state matches, the hand is overwritten in every state and
+    /// `false` is returned.
+    pub fn pin(&mut self, player: usize, cards: Hand) -> bool {
+        if self.states.iter().any(|(state, _)| state[player] == cards) {
+            self.states.retain(|(state, _)| state[player] == cards);
+            return true;
+        }
+        let mut results = HashMap::new();
+        for &(state, count) in &self.states {
+            let mut s_new = state;
+            s_new[player] = cards;
+            *results.entry(s_new).or_insert(0) += count;
+        }
+        self.states = normalize(results);
+        false
+    }
+
+    /// Forces the player's hand to hold `size` cards by adding or discarding
+    /// cards of unknown type. Used to recover after missing events.
+    pub fn resize(&mut self, player: usize, size: u8) {
+        let mut results = HashMap::new();
+        for &(state, count) in &self.states {
+            let current = state[player].size();
+            if current <= size {
+                // any combination of the missing cards is possible
+                for hand in possible_hands(size - current) {
+                    let mut s_new = state;
+                    for (card, num) in hand {
+                        s_new[player][card] += num;
+                    }
+                    *results.entry(s_new).or_insert(0) += count;
+                }
+            } else {
+                // discard the extra cards one at a time, like a rob
+                let mut partial = HashMap::from([(state, count)]);
+                for _ in size..current {
+                    let mut next = HashMap::new();
+                    for (state, count) in partial {
+                        for (card, num) in state[player].into_iter().filter(|(_, c)| *c > 0) {
+                            let mut s_new = state;
+                            s_new[player][card] -= 1;
+                            *next.entry(s_new).or_insert(0) += u32::from(num) * count;
+                        }
+                    }
+                    partial = next;
+                }
+                for (state, count) in partial {
+                    *results.entry(state).or_insert(0) += count;
+                }
+            }
+        }
+        self.states = normalize(results);
+    }
+
+    /// Handles a monopoly event where a `player` steals `count` `card`s from other players.
+    /// Returns `false` if no state has that many, in which case the cards are
+    /// moved in every state, as the other players are left with none either way.
+    pub fn monopoly(&mut self, player: usize, card: Resource, count: u8) -> bool {
+        let matches = |state: &State| {
+            state
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != player) // don't count the monopoler
+                .map(|(_, hand)| hand[card])
+                .sum::<u8>()
+                == count
+        };
+        // remove all states where the count doesn't match the total
+        let consistent = self.states.iter().any(|(state, _)| matches(state));
+        if consistent {
+            self.states.retain(|(state, _)| matches(state));
+        }
+
+        // update the states with the new count
+        let mut results = HashMap::new();
+        for &(state, num) in &self.states {
+            let mut s_new = state;
+            for (i, hand) in s_new.iter_mut().enumerate() {
+                if i == player {
+                    hand[card] += count;
+                } else {
+                    hand[card] = 0;
+                }
+            }
+            *results.entry(s_new).or_insert(0) += num;
+        }
+        self.states = normalize(results);
+        consistent
+    }
+
+    /// Computes the expected value for the number of cards each player has
+    fn expected(&self) -> [[f64; N_RESOURCES]; MAX_PLAYERS] {
+        let n_states = f64::from(self.states.iter().map(|(_, count)| *count).sum::<u32>());
+        let mut expected = <[[f64; N_RESOURCES]; MAX_PLAYERS]>::default();
+        for &(state, count) in &self.states {
+            for (player, cards) in state.iter().enumerate() {
+                for (card, num) in cards.into_iter() {
+                    expected[player][usize::from(card)] +=
+                        f64::from(num) * f64::from(count) / n_states;
+                }
+            }
+        }
+        expected
+    }
+
+    /// Computes the minimum number of cards each player could have
+    fn sure(&self) -> State {
+        let mut sure = self.states[0].0; // there should be at least one
+        for (state, _) in &self.states {
+            for (player, cards) in state.iter().enumerate() {
+                for (card, num) in cards.into_iter() {
+                    sure[player][card] = std::cmp::min(sure[player][card], num);
+                }
+            }
+        }
+        sure
+    }
+
+    /// Computes the expected and minimum value for each card of the player
+    /// (sure, expected, rob chance)
+    pub fn table(&self) -> [[(u8, f64, f64); N_RESOURCES]; MAX_PLAYERS] {
+        let mut table: [[(u8, f64, f64); N_RESOURCES]; MAX_PLAYERS] = Default::default();
+        for (i, (sure, expected)) in self.sure().into_iter().zip(self.expected()).enumerate() {
+            let total = expected.iter().sum::<f64>();
+            for (j, (sure, expected)) in sure.values().zip(expected).enumerate() {
+                let rob_chance = if total == 0.0 { 0.0 } else { expected / total };
+                table[i][j] = (sure, expected, rob_chance);
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_add() {
+        let mut tracker = CardTracker::default();
+        tracker.add(0, Hand::from(Resource::Brick));
+        assert_eq!(tracker.states[0].0[0][Resource::Brick], 1);
+    }
+
+    #[test]
+    fn test_rob_unknown() {
+        let mut tracker = CardTracker::default();
+
+        tracker.add(0, Hand::from([5, 7, 9, 13, 15]));
+        tracker.add(1, Hand::from([12, 11, 6, 5, 3]));
+
+        tracker.rob(1, 0);
+        tracker.rob(1, 0);
+        tracker.rob(1, 0);
+        tracker.rob(0, 1);
+        tracker.rob(0, 1);
+        tracker.rob(0, 1);
+
+        assert_eq!(tracker.states.len(), 471);
+    }
+
+    #[test]
+    fn test_rob_any() {
+        let mut tracker = CardTracker::default();
+        tracker.add(1, Hand::from("ore wool"));
+        tracker.add(2, Hand::from("ore"));
+
+        // the ore is twice as likely to come from the hand that only holds ore
+        assert!(tracker.rob_any(0, Resource::Ore, &[1, 2]));
+        let table = tracker.table();
+        assert_eq!(table[0][Resource::Ore as usize].0, 1);
+        assert!((table[1][Resource::Ore as usize].1 - 2.0 / 3.0).abs() < 1e-9);
+        assert!((table[2][Resource::Ore as usize].1 - 1.0 / 3.0).abs() < 1e-9);
+
+        // nobody has grain to steal, so a message was missed
+        let states = tracker.states.clone();
+        assert!(!tracker.rob_any(0, Resource::Grain, &[1, 2]));
+        assert_eq!(tracker.states, states);
+    }
+
+    #[test]
+    fn test_missed_cards() {
+        let mut tracker = CardTracker::default();
+        tracker.add(0, Hand::from("brick"));
+        tracker.add(1, Hand::from("ore"));
+
+        // the player holds cards the log never gave them
+        assert!(!tracker.know_has(0, Hand::from("lumber")));
+        assert_eq!(tracker.len(), 1);
+        assert!(!tracker.remove(0, Hand::from("brick brick")));
+        assert_eq!(tracker.states[0].0[0], Hand::default());
+        assert!(!tracker.rob(2, 0));
+        assert_eq!(tracker.len(), 1);
+
+        // the other players are left without ore either way
+        assert!(!tracker.monopoly(0, Resource::Ore, 3));
+        assert_eq!(tracker.states[0].0[0], Hand::from("ore ore ore"));
+        assert_eq!(tracker.states[0].0[1], Hand::default());
+        assert!(tracker.monopoly(1, Resource::Ore, 3));
+        assert_eq!(tracker.table()[1][Resource::Ore as usize].0, 3);
+    }
+
+    #[test]
+    fn test_know_size() {
+        let mut tracker = CardTracker::default();
+        tracker.add(0, Hand::from([1, 1, 0, 0, 0]));
+        tracker.add(1, Hand::from([0, 0, 2, 0, 0]));
+        tracker.rob(1, 0);
+        assert!(!tracker.know_size(1, 4));
+        assert_eq!(tracker.len(), 2);
+        assert!(tracker.know_size(1, 3));
+        assert_eq!(tracker.len(), 2);
+    }
+
+    #[test]
+    fn test_pin() {
+        let mut tracker = CardTracker::default();
+        tracker.add(0, Hand::from([1, 1, 0, 0, 0]));
+        tracker.rob(1, 0);
+        assert!(tracker.pin(1, Hand::from(Resource::Brick)));
+        assert_eq!(tracker.len(), 1);
+        assert_eq!(tracker.states[0].0[0], Hand::from(Resource::Lumber));
+
+        assert!(!tracker.pin(1, Hand::from(Resource::Ore)));
+        assert_eq!(tracker.len(), 1);
+        assert_eq!(tracker.states[0].0[1], Hand::from(Resource::Ore));
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut tracker = CardTracker::default();
+        tracker.add(0, Hand::from([2, 1, 0, 0, 0]));
+
+        tracker.resize(0, 2);
+        assert_eq!(tracker.len(), 2);
+        assert!(tracker.states.iter().all(|(s, _)| s[0].size() == 2));
+
+        tracker.resize(0, 3);
+        assert!(tracker.states.iter().all(|(s, _)| s[0].size() == 3));
+        assert_eq!(tracker.table()[0][0].0, 1);
+    }
+}
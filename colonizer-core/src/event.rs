@@ -0,0 +1,256 @@
+//! Game events that change the cards players hold
+use crate::{hand::Hand, resource::Resource};
+use std::fmt;
+
+/// Something a player can buy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Item {
+    /// Costs a lumber and a brick
+    Road,
+    /// Costs a lumber, a brick, a wool and a grain
+    Settlement,
+    /// Costs two grain and three ore
+    City,
+    /// Costs a wool, a grain and an ore
+    DevelopmentCard,
+}
+
+impl Item {
+    /// The cards the item costs
+    pub fn cost(self) -> Hand {
+        match self {
+            Self::Road => Hand::from([1, 1, 0, 0, 0]),
+            Self::Settlement => Hand::from([1, 1, 1, 1, 0]),
+            Self::City => Hand::from([0, 0, 0, 2, 3]),
+            Self::DevelopmentCard => Hand::from([0, 0, 1, 1, 1]),
+        }
+    }
+}
+
+impl TryFrom<&str> for Item {
+    type Error = ();
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "road" => Ok(Self::Road),
+            "settlement" => Ok(Self::Settlement),
+            "city" => Ok(Self::City),
+            "development card" => Ok(Self::DevelopmentCard),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Road => "road",
+            Self::Settlement => "settlement",
+            Self::City => "city",
+            Self::DevelopmentCard => "development card",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A change to the game state, parsed from the game log.
+/// Players are referred to by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Cards received from a roll or the starting settlement
+    Receive {
+        /// Who received them
+        player: String,
+        /// The cards received
+        cards: Hand,
+    },
+    /// Cards discarded on a 7
+    Discard {
+        /// Who discarded them
+        player: String,
+        /// The cards discarded
+        cards: Hand,
+    },
+    /// An item bought with cards
+    Purchase {
+        /// Who bought it
+        player: String,
+        /// What they bought
+        item: Item,
+    },
+    /// A card stolen with the robber
+    Rob {
+        /// Who moved the robber
+        robber: String,
+        /// Who lost the card
+        victim: String,
+        /// `None` when neither player is us, so we can't see it
+        card: Option<Resource>,
+    },
+    /// A rob from the player whose view the page shows, while spectating
+    /// without knowing who that is
+    RobFromUnknown {
+        /// Who moved the robber
+        robber: String,
+        /// The card stolen
+        card: Resource,
+    },
+    /// Cards offered to the other players
+    TradeOffer {
+        /// Who offered them
+        player: String,
+        /// The cards offered
+        offer: Hand,
+    },
+    /// A trade between two players
+    Trade {
+        /// Who offered the trade
+        player: String,
+        /// Who accepted it
+        counterparty: String,
+        /// The cards going from `player` to `counterparty`
+        offer: Hand,
+        /// The cards going from `counterparty` to `player`
+        request: Hand,
+    },
+    /// Cards taken from the bank with a Year of Plenty
+    YearOfPlenty {
+        /// Who played the card
+        player: String,
+        /// The cards taken
+        cards: Hand,
+    },
+    /// A trade with the bank or a harbor
+    BankTrade {
+        /// Who traded
+        player: String,
+        /// The cards given to the bank
+        given: Hand,
+        /// The cards taken from the bank
+        taken: Hand,
+    },
+    /// Cards of one resource taken from the other players
+    Monopoly {
+        /// Who played the card
+        player: String,
+        /// The resource taken
+        resource: Resource,
+        /// How many cards were taken in all
+        count: u8,
+    },
+    /// A development card played
+    DevCardUsed {
+        /// Who played it
+        player: String,
+    },
+    /// A free settlement or road during the setup
+    Placement {
+        /// Who placed it
+        player: String,
+        /// What they placed
+        item: Item,
+    },
+    /// Starts the player's turn
+    Roll {
+        /// Whose turn it is
+        player: String,
+        /// The two dice
+        dice: [u8; 2],
+    },
+    /// The player left and a bot plays their seat
+    Left {
+        /// Who left
+        player: String,
+    },
+    /// The player took their seat back from the bot
+    Rejoined {
+        /// Who rejoined
+        player: String,
+    },
+}
+
+impl Event {
+    /// The event with the names of the players involved replaced by
+    /// `rename`'s, or the first error it returns
+    pub fn try_rename_players<E>(
+        mut self,
+        mut rename: impl FnMut(&str) -> Result<String, E>,
+    ) -> Result<Self, E> {
+        for name in self.players_mut() {
+            *name = rename(name)?;
+        }
+        Ok(self)
+    }
+
+    /// The names of the players involved
+    pub(crate) fn players_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Self::Rob { robber, victim, .. } => vec![robber, victim],
+            Self::Trade {
+                player,
+                counterparty,
+                ..
+            } => vec![player, counterparty],
+            Self::RobFromUnknown { robber: player, .. }
+            | Self::Receive { player, .. }
+            | Self::Discard { player, .. }
+            | Self::Purchase { player, .. }
+            | Self::TradeOffer { player, .. }
+            | Self::YearOfPlenty { player, .. }
+            | Self::BankTrade { player, .. }
+            | Self::Monopoly { player, .. }
+            | Self::DevCardUsed { player }
+            | Self::Left { player }
+            | Self::Rejoined { player }
+            | Self::Placement { player, .. }
+            | Self::Roll { player, .. } => vec![player],
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Receive { player, cards } => write!(f, "{player} got {cards}"),
+            Self::Discard { player, cards } => write!(f, "{player} discarded {cards}"),
+            Self::Purchase { player, item } => write!(f, "{player} purchased {item}"),
+            Self::Rob {
+                robber,
+                victim,
+                card,
+            } => match card {
+                Some(card) => write!(f, "{robber} stole {} from {victim}", card.name()),
+                None => write!(f, "{robber} stole card from {victim}"),
+            },
+            Self::RobFromUnknown { robber, card } => {
+                write!(f, "{robber} stole {} from someone", card.name())
+            }
+            Self::TradeOffer { player, offer } => write!(f, "{player} offered {offer}"),
+            Self::Trade {
+                player,
+                counterparty,
+                offer,
+                request,
+            } => write!(
+                f,
+                "{player} traded {offer} for {request} with {counterparty}"
+            ),
+            Self::YearOfPlenty { player, cards } => write!(f, "{player} took from bank {cards}"),
+            Self::BankTrade {
+                player,
+                given,
+                taken,
+            } => write!(f, "{player} gave bank {given} for {taken}"),
+            Self::Monopoly {
+                player,
+                resource,
+                count,
+            } => write!(f, "{player} monopolied {count} {}", resource.name()),
+            Self::DevCardUsed { player } => write!(f, "{player} used a development card"),
+            Self::Placement { player, item } => write!(f, "{player} placed {item}"),
+            Self::Roll { player, dice } => write!(f, "{player} rolled {} {}", dice[0], dice[1]),
+            Self::Left { player } => write!(f, "{player} left, a bot took over"),
+            Self::Rejoined { player } => write!(f, "{player} rejoined"),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+//! The cards a player holds
 use crate::resource::{Resource, N_RESOURCES};
 use regex::Regex;
 use std::{
@@ -5,6 +6,7 @@ use std::{
     ops::{Index, IndexMut},
 };
 
+/// A number of cards of each resource, e.g. `Hand::from("lumber lumber ore")`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct Hand([u8; N_RESOURCES]);
 
@@ -47,9 +49,15 @@ impl IntoIterator for Hand {
 }
 
 impl Hand {
+    /// The number of cards of each resource, in the order of `Resource`
     pub fn values(self) -> std::array::IntoIter<u8, N_RESOURCES> {
         self.0.into_iter()
     }
+
+    /// Returns the total number of cards in the hand
+    pub fn size(self) -> u8 {
+        self.0.iter().sum()
+    }
 }
 
 impl From<[u8; N_RESOURCES]> for Hand {
@@ -58,6 +66,17 @@ impl From<[u8; N_RESOURCES]> for Hand {
     }
 }
 
+impl std::fmt::Display for Hand {
+    /// Lists each card by name, the inverse of `Hand::from(&str)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self
+            .into_iter()
+            .flat_map(|(card, count)| std::iter::repeat_n(card.name(), usize::from(count)))
+            .collect::<Vec<_>>();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
 impl From<&str> for Hand {
     fn from(value: &str) -> Self {
         let re = Regex::new(r"(lumber|brick|wool|grain|ore)").unwrap();
@@ -71,28 +90,28 @@ impl From<&str> for Hand {
     }
 }
 
-// /// Finds the possible hands given the number of cards.
-// /// Stars and bars algorithm with fixed k=`N_RESOURCES`
-// pub fn possible_hands(count: u8) -> Vec<Hand> {
-//     let mut result = Vec::new();
-//     let mut bins = [0; N_RESOURCES];
-//     bins[0] = count;
-//     loop {
-//         result.push(Hand::from(bins));
-//         if *bins.last().unwrap() == count {
-//             return result;
-//         }
-//         if bins[0] > 0 {
-//             bins[0] -= 1;
-//             bins[1] += 1;
-//         } else {
-//             let mut i = 1;
-//             while bins[i] == 0 {
-//                 i += 1;
-//             }
-//             bins[0] = bins[i] - 1;
-//             bins[i + 1] += 1;
-//             bins[i] = 0;
-//         }
-//     }
-// }
+/// Finds the possible hands given the number of cards.
+/// Stars and bars algorithm with fixed k=`N_RESOURCES`
+pub(crate) fn possible_hands(count: u8) -> Vec<Hand> {
+    let mut result = Vec::new();
+    let mut bins = [0; N_RESOURCES];
+    bins[0] = count;
+    loop {
+        result.push(Hand::from(bins));
+        if *bins.last().unwrap() == count {
+            return result;
+        }
+        if bins[0] > 0 {
+            bins[0] -= 1;
+            bins[1] += 1;
+        } else {
+            let mut i = 1;
+            while bins[i] == 0 {
+                i += 1;
+            }
+            bins[0] = bins[i] - 1;
+            bins[i + 1] += 1;
+            bins[i] = 0;
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Reads the log and the player panels from the html of the game page
+use crate::{hand::Hand, resource::Resource};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+
+/// Selectors for the player panels on the right side of the game
+const PLAYER_PANEL: &str = ".player-card";
+const PLAYER_NAME: &str = ".player-name";
+const RESOURCE_COUNT: &str = ".resource-card-count";
+const DEV_CARD_COUNT: &str = ".dev-card-count";
+/// Selectors for our own hand at the bottom of the game
+const HAND: &str = ".hand-cards";
+const HAND_CARD: &str = "img";
+/// The images of the development cards, as in `card_knight.svg`
+const DEV_CARDS: [&str; 5] = ["knight", "vp", "monopoly", "roadbuilding", "yearofplenty"];
+
+/// Card counts displayed in a player's panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerCounts {
+    /// The name of the player the panel is for
+    pub name: String,
+    /// The number of resource cards
+    pub resources: u8,
+    /// The number of development cards
+    pub dev_cards: u8,
+}
+
+/// The cards in our own hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OwnHand {
+    /// The resource cards
+    pub resources: Hand,
+    /// The number of development cards
+    pub dev_cards: u8,
+}
+
+/// Returns the text of an element with images replaced by their alt-text
+fn element_text(element: ElementRef) -> String {
+    let mut text = String::new();
+    for node in element.descendants() {
+        if let Some(t) = node.value().as_text() {
+            text.push_str(t);
+        } else if let Some(alt) = node.value().as_element().and_then(|e| e.attr("alt")) {
+            text.push_str(alt);
+        }
+    }
+    text.trim().to_owned()
+}
+
+/// Parses the resource and development card counts from the player panels
+pub fn parse_player_counts(html: &str) -> Vec<PlayerCounts> {
+    let document = Html::parse_fragment(html);
+    let panel_selector = Selector::parse(PLAYER_PANEL).unwrap();
+    let name_selector = Selector::parse(PLAYER_NAME).unwrap();
+    let resource_selector = Selector::parse(RESOURCE_COUNT).unwrap();
+    let dev_card_selector = Selector::parse(DEV_CARD_COUNT).unwrap();
+
+    let mut counts = Vec::new();
+    for panel in document.select(&panel_selector) {
+        let field = |selector: &Selector| panel.select(selector).next().map(element_text);
+        let (Some(name), Some(resources), Some(dev_cards)) = (
+            field(&name_selector),
+            field(&resource_selector),
+            field(&dev_card_selector),
+        ) else {
+            continue;
+        };
+        let (Ok(resources), Ok(dev_cards)) = (resources.parse(), dev_cards.parse()) else {
+            continue;
+        };
+        counts.push(PlayerCounts {
+            name,
+            resources,
+            dev_cards,
+        });
+    }
+    counts
+}
+
+/// Parses our own resource and development cards from the hand UI.
+/// Cards are identified by their image, e.g. `card_lumber.svg` or `card_knight.svg`.
+/// Returns `None` if the hand isn't on the page, or if it shows a card that's
+/// neither, like the back of a card, as the hand was then misread.
+pub fn parse_own_hand(html: &str) -> Option<OwnHand> {
+    let document = Html::parse_fragment(html);
+    let hand_selector = Selector::parse(HAND).unwrap();
+    let card_selector = Selector::parse(HAND_CARD).unwrap();
+    let pattern = Regex::new(r"card_(\w+)\.svg").unwrap();
+
+    let container = document.select(&hand_selector).next()?;
+    let mut hand = OwnHand::default();
+    for card in container.select(&card_selector) {
+        let Some(captures) = card
+            .value()
+            .attr("src")
+            .and_then(|src| pattern.captures(src))
+        else {
+            continue;
+        };
+        match Resource::try_from(&captures[1]) {
+            Ok(resource) => hand.resources[resource] += 1,
+            Err(()) if DEV_CARDS.contains(&&captures[1]) => hand.dev_cards += 1,
+            Err(()) => return None,
+        }
+    }
+    Some(hand)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The image of a card as the log shows it
+    fn card_image(name: &str) -> String {
+        let html = std::fs::read_to_string("../games/game2.html").unwrap();
+        let document = Html::parse_document(&html);
+        let selector = Selector::parse(&format!(r#"img[src*="card_{name}.svg"]"#)).unwrap();
+        document.select(&selector).next().unwrap().html()
+    }
+
+    #[test]
+    fn test_parse_own_hand() {
+        let hand = |cards: &[&str]| {
+            let images: String = cards.iter().map(|&name| card_image(name)).collect();
+            parse_own_hand(&format!(r#"<div class="hand-cards">{images}</div>"#))
+        };
+        assert_eq!(
+            hand(&["lumber", "ore", "ore", "knight", "monopoly"]),
+            Some(OwnHand {
+                resources: Hand::from("lumber ore ore"),
+                dev_cards: 2,
+            })
+        );
+        // the backs of cards aren't in our hand
+        assert_eq!(hand(&["lumber", "rescardback"]), None);
+        assert_eq!(hand(&["devcardback"]), None);
+        assert_eq!(parse_own_hand("<div></div>"), None);
+    }
+}
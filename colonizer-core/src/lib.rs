@@ -0,0 +1,43 @@
+//! Tracks the cards of the players of a [colonist.io](https://colonist.io) game
+//! from its log.
+//!
+//! The log is parsed into [`message::Message`]s, which describe
+//! [`event::Event`]s. A [`card_tracker::CardTracker`] keeps every state of the
+//! players' [`hand::Hand`]s the events so far allow, as robs between other
+//! players can't be seen.
+//!
+//! ```
+//! use colonizer_core::card_tracker::CardTracker;
+//! use colonizer_core::hand::Hand;
+//! use colonizer_core::message::parse_messages;
+//! use colonizer_core::event::Event;
+//!
+//! let log = r#"<div class="message_post" style="color: rgb(226, 113, 116);"><img src="/dist/images/icon_bot.svg" alt="bot">Brig got: <img src="/dist/images/card_lumber.svg" alt="lumber"><img src="/dist/images/card_ore.svg" alt="ore"></div>"#;
+//! let event = parse_messages(log)[0].event(None).unwrap();
+//! assert_eq!(
+//!     event,
+//!     Event::Receive {
+//!         player: "Brig".to_owned(),
+//!         cards: Hand::from("lumber ore"),
+//!     }
+//! );
+//!
+//! let mut tracker = CardTracker::default();
+//! tracker.add(0, Hand::from("lumber ore"));
+//! tracker.add(1, Hand::from("wool"));
+//! assert!(tracker.rob(0, 1));
+//! // Brig robbed the only card the other player had
+//! assert_eq!(tracker.len(), 1);
+//! ```
+//!
+//! The public API follows semver: check changes with
+//! [`cargo semver-checks`](https://github.com/obi1kenobi/cargo-semver-checks)
+//! before releasing.
+#![warn(missing_docs)]
+
+pub mod card_tracker;
+pub mod event;
+pub mod hand;
+pub mod html_parser;
+pub mod message;
+pub mod resource;
@@ -0,0 +1,862 @@
+//! Parses the messages of the game log into tokens, and the tokens into events.
+//!
+//! Player names are plain text in the log, with the player's avatar in front of
+//! the name of the player the message is about. Words in names that match a
+//! resource are shown as the resource's card, e.g. `[brick]dUpp`, so names are
+//! recovered from their position in the message rather than from the text alone.
+use crate::event::{Event, Item};
+use crate::hand::Hand;
+use crate::resource::Resource;
+use lazy_static::lazy_static;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+lazy_static! {
+    static ref COLOR: Regex = Regex::new(r"(\d+), (\d+), (\d+)").unwrap();
+    /// The file name of an image, without the path and the cache buster
+    static ref IMAGE: Regex = Regex::new(r"([\w-]+)\.svg").unwrap();
+    static ref MONOPOLY: Regex = Regex::new(r"^stole (\d+):$").unwrap();
+}
+
+/// What a player did, following their name
+const VERBS: [&str; 22] = [
+    "got:",
+    "received starting resources:",
+    "received",
+    "rolled:",
+    "wants to give",
+    "gave bank:",
+    "built a",
+    "placed a",
+    "bought",
+    "stole",
+    "discarded:",
+    "used",
+    "traded:",
+    "took from bank:",
+    "moved robber",
+    "won the game!",
+    "has left the game",
+    "left the game",
+    "has reconnected",
+    "reconnected",
+    "has rejoined",
+    "rejoined",
+];
+
+/// Verbs followed by another player's name at the end of the message
+const OBJECT_VERBS: [&str; 2] = ["stole", "traded:"];
+/// Precede the other player's name, from the most specific
+const OBJECT_KEYWORDS: [&str; 3] = ["from:", "with:", "from "];
+
+/// The color of the messages the game posts about the lobby, rather than
+/// about a player
+const NOTICE_COLOR: (u8, u8, u8) = (102, 102, 102);
+/// How the notices the game posts at the start of every log begin
+const WELCOME: [&str; 2] = ["List of Commands", "Learn how to play"];
+
+/// A word or image of a message
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Token {
+    /// A player, with the avatar in front of their name if it has one, e.g.
+    /// `Guest`, `User` or `bot`
+    Player {
+        /// The name shown in the log
+        name: String,
+        /// The avatar's alt text
+        icon: Option<String>,
+    },
+    /// "you", which refers to us
+    You,
+    /// A resource card
+    Card(Resource),
+    /// The back of a resource card, for robs we can't see
+    HiddenCard,
+    /// The back of a development card
+    DevelopmentCard,
+    /// A die of a roll
+    Dice(u8),
+    /// The number on a tile
+    Number(u8),
+    /// `None` for the desert
+    Tile(Option<Resource>),
+    /// A road, settlement or city
+    Piece(Item),
+    /// Any other image, by its alt text, e.g. `robber` or `trophy`
+    Icon(String),
+    /// Words between the images, with their spacing collapsed
+    Text(String),
+}
+
+/// Shows the token as the log does, with the images by name
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Player { name, .. } => write!(f, "{name}"),
+            Self::You => write!(f, "you"),
+            Self::Card(resource) => write!(f, "{}", resource.name()),
+            Self::HiddenCard => write!(f, "card"),
+            Self::DevelopmentCard => write!(f, "development card"),
+            Self::Dice(n) | Self::Number(n) => write!(f, "{n}"),
+            Self::Tile(Some(resource)) => write!(f, "{} tile", resource.name()),
+            Self::Tile(None) => write!(f, "desert"),
+            Self::Piece(item) => write!(f, "{item}"),
+            Self::Icon(text) | Self::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+/// A piece of a message before the names are resolved
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Piece {
+    /// The avatar in front of a player's name
+    Avatar(String),
+    Token(Token),
+    /// Text as it is in the html, with its spacing
+    Text(String),
+}
+
+impl Piece {
+    /// The text this piece shows when it's part of a name
+    fn name_part(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            Self::Token(Token::Card(resource)) => Some(resource.name()),
+            _ => None,
+        }
+    }
+}
+
+/// A chat message typed by a player
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chat {
+    /// The name of who typed it
+    pub player: String,
+    /// What they typed
+    pub text: String,
+}
+
+/// Who posted a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    /// Posted by the game about a player, starting with their avatar or an icon
+    Event,
+    /// Typed by a player, as "name: text" in their color
+    Chat(Chat),
+    /// Posted by the game about the lobby or the board, e.g. the karma system
+    /// or blocked tiles
+    Notice,
+}
+
+/// A `.message_post` of the log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// The color of the text, which is the player's for chats
+    pub color: (u8, u8, u8),
+    /// Who posted it
+    pub kind: Kind,
+    /// The words and images of the message, with the names resolved
+    pub tokens: Vec<Token>,
+    /// The message before its names are resolved
+    pieces: Vec<Piece>,
+}
+
+fn parse_color(style: &str) -> (u8, u8, u8) {
+    COLOR.captures(style).map_or((0, 0, 0), |caps| {
+        (
+            caps[1].parse().unwrap_or_default(),
+            caps[2].parse().unwrap_or_default(),
+            caps[3].parse().unwrap_or_default(),
+        )
+    })
+}
+
+/// Classifies an image by its file name
+fn image(src: &str, alt: &str) -> Piece {
+    let name = IMAGE
+        .captures(src)
+        .map_or("", |caps| caps.get(1).unwrap().as_str());
+    let (kind, rest) = name.split_once('_').unwrap_or((name, ""));
+    let token = match (kind, rest) {
+        ("icon", icon) if icon.starts_with("player") || icon == "bot" => {
+            return Piece::Avatar(alt.to_owned())
+        }
+        ("card", "rescardback") => Token::HiddenCard,
+        ("card", "devcardback") => Token::DevelopmentCard,
+        ("card", card) => match Resource::try_from(card) {
+            Ok(resource) => Token::Card(resource),
+            Err(()) => Token::Icon(alt.to_owned()),
+        },
+        ("dice", n) => n.parse().map_or(Token::Icon(alt.to_owned()), Token::Dice),
+        ("prob", n) => n.parse().map_or(Token::Icon(alt.to_owned()), Token::Number),
+        ("tile", tile) => Token::Tile(Resource::try_from(tile).ok()),
+        (piece, _) => match Item::try_from(piece) {
+            Ok(item) if item != Item::DevelopmentCard => Token::Piece(item),
+            _ => Token::Icon(alt.to_owned()),
+        },
+    };
+    Piece::Token(token)
+}
+
+/// The images and text of a message, in order
+fn pieces(message: ElementRef) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    for node in message.descendants().skip(1) {
+        match node.value() {
+            Node::Text(text) => match pieces.last_mut() {
+                // text is split around links
+                Some(Piece::Text(last)) => last.push_str(text),
+                _ => pieces.push(Piece::Text(text.to_string())),
+            },
+            Node::Element(element) if element.name() == "img" => pieces.push(image(
+                element.attr("src").unwrap_or_default(),
+                element.attr("alt").unwrap_or_default(),
+            )),
+            _ => {}
+        }
+    }
+    pieces
+}
+
+/// A name as written in the log
+fn player(name: &str, icon: Option<String>) -> Token {
+    match name {
+        "you" | "You" => Token::You,
+        _ => Token::Player {
+            name: name.to_owned(),
+            icon,
+        },
+    }
+}
+
+/// Finds the first verb in `text` that starts a word, returning where it starts
+fn find_verb(text: &str) -> Option<(usize, &'static str)> {
+    text.char_indices()
+        .filter(|&(i, _)| i > 0 && text[..i].ends_with(char::is_whitespace))
+        .find_map(|(i, _)| {
+            VERBS
+                .iter()
+                .find(|verb| text[i..].starts_with(*verb))
+                .map(|verb| (i, *verb))
+        })
+}
+
+/// The known players by length, so a name isn't matched by a shorter name it
+/// starts or ends with
+fn by_length(names: &HashSet<String>) -> Vec<&str> {
+    let mut names: Vec<_> = names.iter().map(String::as_str).collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    names
+}
+
+/// Finds where the verb after the name at the start of `text` starts. Known
+/// names are tried first, since a name can contain a verb, and otherwise the
+/// name ends at the first verb.
+fn find_subject(text: &str, names: &HashSet<String>) -> Option<(usize, &'static str)> {
+    let start = text.len() - text.trim_start().len();
+    let known = by_length(names).into_iter().find_map(|name| {
+        let after = text[start..].strip_prefix(name)?;
+        let rest = after.trim_start();
+        if rest.len() == after.len() {
+            return None;
+        }
+        let verb = VERBS.iter().find(|verb| rest.starts_with(*verb))?;
+        Some((text.len() - rest.len(), *verb))
+    });
+    known.or_else(|| find_verb(&text[start..]).map(|(at, verb)| (start + at, verb)))
+}
+
+/// Finds where the name after the last keyword at the end of `text` starts.
+/// Known names are tried first, since a name can contain a keyword.
+fn find_object(text: &str, names: &HashSet<String>) -> Option<usize> {
+    let text = text.trim_end();
+    let known = by_length(names).into_iter().find_map(|name| {
+        let before = text.strip_suffix(name)?;
+        OBJECT_KEYWORDS
+            .iter()
+            .any(|keyword| before.trim_end().ends_with(keyword.trim_end()))
+            .then_some(before.len())
+    });
+    known.or_else(|| {
+        OBJECT_KEYWORDS
+            .iter()
+            .find_map(|keyword| text.rfind(keyword).map(|at| at + keyword.len()))
+    })
+}
+
+/// The text of the pieces that can be part of a name, up to the first piece
+/// that can't, and where each of those pieces starts in it
+fn name_text(pieces: &[Piece]) -> (String, Vec<usize>) {
+    let mut text = String::new();
+    let mut offsets = Vec::new();
+    for piece in pieces {
+        let Some(part) = piece.name_part() else {
+            break;
+        };
+        offsets.push(text.len());
+        text.push_str(part);
+    }
+    (text, offsets)
+}
+
+/// Splits the pieces at `at` in their `name_text`, returning how many pieces
+/// come before it and the rest of the piece it falls in, if it's text
+fn split_at(pieces: &[Piece], offsets: &[usize], at: usize) -> (usize, Option<(String, String)>) {
+    let i = offsets
+        .iter()
+        .rposition(|&offset| offset <= at)
+        .unwrap_or(0);
+    match &pieces[i] {
+        Piece::Text(text) if at > offsets[i] => {
+            let (before, after) = text.split_at(at - offsets[i]);
+            (i, Some((before.to_owned(), after.to_owned())))
+        }
+        _ => (i, None),
+    }
+}
+
+/// Turns the text in front of the verb into the player the message is about.
+/// The name starts after the avatar or icon, and can span several pieces.
+fn resolve_subject(pieces: &mut Vec<Piece>, names: &HashSet<String>) -> Option<&'static str> {
+    let mut start = 0;
+    let mut icon = None;
+    while let Some(piece) = pieces.get(start) {
+        match piece {
+            Piece::Avatar(alt) => icon = Some(alt.clone()),
+            Piece::Token(Token::Icon(_)) => {}
+            Piece::Text(text) if text.trim().is_empty() => {}
+            _ => break,
+        }
+        start += 1;
+    }
+    let (text, offsets) = name_text(&pieces[start..]);
+    let (at, verb) = find_subject(&text, names)?;
+    let name = text[..at].trim();
+    if name.is_empty() {
+        return None;
+    }
+    // the verb is always text
+    let (i, Some((_, rest))) = split_at(&pieces[start..], &offsets, at) else {
+        return None;
+    };
+    let subject = Piece::Token(player(name, icon));
+    pieces.splice(start..=start + i, [subject, Piece::Text(rest)]);
+    Some(verb)
+}
+
+/// Turns the text after the last keyword into the other player of a rob or
+/// trade. The name can start with cards, so it can span several pieces.
+fn resolve_object(pieces: &mut Vec<Piece>, names: &HashSet<String>) {
+    let start = pieces
+        .iter()
+        .rposition(|piece| piece.name_part().is_none())
+        .map_or(0, |i| i + 1);
+    let (text, offsets) = name_text(&pieces[start..]);
+    let Some(at) = find_object(&text, names) else {
+        return;
+    };
+    let name = text[at..].trim();
+    if name.is_empty() {
+        return;
+    }
+    let object = Piece::Token(player(name, None));
+    match split_at(&pieces[start..], &offsets, at) {
+        (i, Some((keyword, _))) => {
+            pieces.splice(start + i.., [Piece::Text(keyword), object]);
+        }
+        (i, None) => {
+            pieces.splice(start + i.., [object]);
+        }
+    }
+}
+
+/// Tells events from chat and notices by how the message starts. Only events
+/// start with an image, which players can't type.
+fn classify(pieces: &[Piece], color: (u8, u8, u8)) -> Kind {
+    let first = pieces
+        .iter()
+        .find(|piece| !matches!(piece, Piece::Text(text) if text.trim().is_empty()));
+    if matches!(first, Some(Piece::Avatar(_) | Piece::Token(Token::Icon(_)))) {
+        return Kind::Event;
+    }
+    if color == NOTICE_COLOR {
+        return Kind::Notice;
+    }
+    // names starting with a resource are shown with the resource's card
+    let text = pieces
+        .iter()
+        .map(Piece::name_part)
+        .collect::<Option<String>>();
+    let chat = text.as_deref().and_then(|text| {
+        let (player, text) = text.split_once(": ")?;
+        let player = player.trim();
+        (!player.is_empty()).then(|| Chat {
+            player: player.to_owned(),
+            text: text.trim().to_owned(),
+        })
+    });
+    chat.map_or(Kind::Notice, Kind::Chat)
+}
+
+impl Message {
+    /// Parses a `.message_post` element
+    fn parse(message: ElementRef) -> Self {
+        let color = parse_color(message.value().attr("style").unwrap_or_default());
+        let pieces = pieces(message);
+        let mut message = Self {
+            color,
+            kind: classify(&pieces, color),
+            tokens: Vec::new(),
+            pieces,
+        };
+        message.resolve(&HashSet::new());
+        message
+    }
+
+    /// Finds the names of the players in the message, preferring the players
+    /// in `names` to splitting the text at the first verb
+    pub fn resolve(&mut self, names: &HashSet<String>) {
+        let mut pieces = self.pieces.clone();
+        // players can type anything, so only the game's own messages can be
+        // trusted to describe events
+        if !matches!(self.kind, Kind::Chat(_))
+            && resolve_subject(&mut pieces, names).is_some_and(|verb| OBJECT_VERBS.contains(&verb))
+        {
+            resolve_object(&mut pieces, names);
+        }
+        self.tokens = pieces
+            .into_iter()
+            .filter_map(|piece| match piece {
+                Piece::Avatar(alt) => Some(Token::Icon(alt)),
+                Piece::Token(token) => Some(token),
+                Piece::Text(text) => {
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    (!text.is_empty()).then_some(Token::Text(text))
+                }
+            })
+            .collect();
+    }
+
+    /// The message as plain text, without the avatars
+    pub fn text(&self) -> String {
+        let avatar = |(token, next): (&Token, Option<&Token>)| matches!((token, next), (Token::Icon(icon), Some(Token::Player { icon: Some(avatar), .. })) if icon == avatar);
+        self.tokens
+            .iter()
+            .zip(self.tokens.iter().skip(1).map(Some).chain([None]))
+            .filter(|&pair| !avatar(pair))
+            .map(|(token, _)| token.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The player the message is about and what they did, e.g. `got:`
+    pub fn subject(&self) -> Option<(&Token, &str)> {
+        let i = self
+            .tokens
+            .iter()
+            .position(|t| matches!(t, Token::Player { .. } | Token::You))?;
+        match self.tokens.get(i + 1) {
+            Some(Token::Text(verb)) => Some((&self.tokens[i], verb)),
+            _ => None,
+        }
+    }
+
+    /// The event this message describes, if any. "you" refers to `you`, and
+    /// messages about "you" have no event while we don't know who that is.
+    pub fn event(&self, you: Option<&str>) -> Option<Event> {
+        let name = |token: &Token| match token {
+            Token::Player { name, .. } => Some(name.clone()),
+            Token::You => you.map(str::to_owned),
+            _ => None,
+        };
+        let (subject, verb) = self.subject()?;
+        let player = name(subject)?;
+        let i = self.tokens.iter().position(|t| t == subject)?;
+        let args = &self.tokens[i + 2..];
+
+        let event = match (verb, args) {
+            ("got:" | "received starting resources:", cards) => Event::Receive {
+                player,
+                cards: hand(cards)?,
+            },
+            ("discarded:", cards) => Event::Discard {
+                player,
+                cards: hand(cards)?,
+            },
+            ("built a", [Token::Piece(item), ..]) => Event::Purchase {
+                player,
+                item: *item,
+            },
+            ("bought", [Token::DevelopmentCard, ..]) => Event::Purchase {
+                player,
+                item: Item::DevelopmentCard,
+            },
+            ("placed a", [Token::Piece(item), ..]) => Event::Placement {
+                player,
+                item: *item,
+            },
+            ("rolled:", [Token::Dice(a), Token::Dice(b), ..]) => Event::Roll {
+                player,
+                dice: [*a, *b],
+            },
+            ("stole:" | "stole", [card, Token::Text(from), victim]) if from.starts_with("from") => {
+                let card = match card {
+                    Token::Card(resource) => Some(*resource),
+                    Token::HiddenCard => None,
+                    _ => return None,
+                };
+                if let (Token::You, None, Some(card)) = (victim, you, card) {
+                    return Some(Event::RobFromUnknown {
+                        robber: player,
+                        card,
+                    });
+                }
+                Event::Rob {
+                    robber: player,
+                    victim: name(victim)?,
+                    card,
+                }
+            }
+            ("wants to give:", rest) => {
+                let (offer, rest) = split_cards(rest)?;
+                match rest {
+                    [Token::Text(t), ..] if t == "for:" => Event::TradeOffer { player, offer },
+                    _ => return None,
+                }
+            }
+            ("traded:", rest) => {
+                let (offer, rest) = split_cards(rest)?;
+                let [Token::Text(f), rest @ ..] = rest else {
+                    return None;
+                };
+                let (request, rest) = split_cards(rest)?;
+                match (f.as_str(), rest) {
+                    ("for:", [Token::Text(with), counterparty]) if with == "with:" => {
+                        Event::Trade {
+                            player,
+                            counterparty: name(counterparty)?,
+                            offer,
+                            request,
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+            ("took from bank:", cards) => Event::YearOfPlenty {
+                player,
+                cards: hand(cards)?,
+            },
+            ("gave bank:", rest) => {
+                let (given, rest) = split_cards(rest)?;
+                match rest {
+                    [Token::Text(and), rest @ ..] if and == "and took" => Event::BankTrade {
+                        player,
+                        given,
+                        taken: hand(rest)?,
+                    },
+                    _ => return None,
+                }
+            }
+            (verb, _) if verb.contains("left the game") => Event::Left { player },
+            (verb, _) if verb.contains("reconnected") || verb.contains("rejoined") => {
+                Event::Rejoined { player }
+            }
+            // the card's name follows, e.g. "used Knight"
+            (verb, _) if verb.starts_with("used ") => Event::DevCardUsed { player },
+            (verb, [Token::Card(resource), ..]) => {
+                let count = MONOPOLY.captures(verb)?[1].parse().ok()?;
+                Event::Monopoly {
+                    player,
+                    resource: *resource,
+                    count,
+                }
+            }
+            _ => return None,
+        };
+        Some(event)
+    }
+
+    /// Identifies the message by its color and what it shows, so a log can be
+    /// matched against the messages read before
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.color.hash(&mut hasher);
+        self.pieces.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The player who won, if this message ends the game
+    pub fn winner(&self) -> Option<&str> {
+        match self.subject()? {
+            (Token::Player { name, .. }, "won the game!") => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Whether this is one of the notices that start a new game's log
+    pub fn is_welcome(&self) -> bool {
+        self.kind == Kind::Notice
+            && matches!(self.tokens.first(), Some(Token::Text(text))
+                if WELCOME.iter().any(|welcome| text.starts_with(welcome)))
+    }
+}
+
+/// Splits the leading cards from the rest of the tokens
+fn split_cards(tokens: &[Token]) -> Option<(Hand, &[Token])> {
+    let count = tokens
+        .iter()
+        .take_while(|t| matches!(t, Token::Card(_)))
+        .count();
+    let (cards, rest) = tokens.split_at(count);
+    Some((hand(cards)?, rest))
+}
+
+/// The cards in `tokens`, which must all be cards
+fn hand(tokens: &[Token]) -> Option<Hand> {
+    let mut hand = Hand::default();
+    for token in tokens {
+        match token {
+            Token::Card(resource) => hand[*resource] += 1,
+            _ => return None,
+        }
+    }
+    (!tokens.is_empty()).then_some(hand)
+}
+
+/// Parses the messages of a log, skipping the separators between turns
+pub fn parse_messages(html: &str) -> Vec<Message> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse(".message_post").unwrap();
+    let separator = Selector::parse("hr").unwrap();
+    document
+        .select(&selector)
+        .filter(|message| message.select(&separator).next().is_none())
+        .map(Message::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOT: &str = r#"<img src="/dist/images/icon_bot.svg?v149" alt="bot">"#;
+    const GUEST: &str = r#"<img src="/dist/images/icon_player.svg?v149" alt="Guest">"#;
+
+    fn card(name: &str) -> String {
+        format!(r#"<img src="/dist/images/card_{name}.svg?v149" alt="{name}">"#)
+    }
+
+    fn message(body: &str) -> Message {
+        let html =
+            format!(r#"<div class="message_post" style="color: rgb(34, 54, 151);">{body}</div>"#);
+        parse_messages(&html).remove(0)
+    }
+
+    fn event(body: &str) -> Option<Event> {
+        message(body).event(Some("Magee#8353"))
+    }
+
+    #[test]
+    fn test_tokens() {
+        let msg = message(&format!(
+            r#"{BOT}Meras rolled: <img src="/dist/images/dice_3.svg" alt="dice_3"> <img src="/dist/images/dice_5.svg" alt="dice_5">"#
+        ));
+        assert_eq!(msg.color, (34, 54, 151));
+        assert_eq!(
+            msg.tokens,
+            vec![
+                Token::Icon("bot".to_owned()),
+                Token::Player {
+                    name: "Meras".to_owned(),
+                    icon: Some("bot".to_owned())
+                },
+                Token::Text("rolled:".to_owned()),
+                Token::Dice(3),
+                Token::Dice(5),
+            ]
+        );
+        assert_eq!(msg.text(), "Meras rolled: 3 5");
+    }
+
+    #[test]
+    fn test_names() {
+        // a name with a colon and non-ascii letters
+        assert_eq!(
+            event(&format!("{GUEST}Zoë: the 2nd got: {}", card("ore"))),
+            Some(Event::Receive {
+                player: "Zoë: the 2nd".to_owned(),
+                cards: Hand::from("ore")
+            })
+        );
+        // a name that starts with a resource is shown with the resource's card
+        assert_eq!(
+            event(&format!(
+                "{GUEST}{}dUpp traded: {} for: {} with: {}Young",
+                card("brick"),
+                card("wool"),
+                card("grain"),
+                card("lumber")
+            )),
+            Some(Event::Trade {
+                player: "brickdUpp".to_owned(),
+                counterparty: "lumberYoung".to_owned(),
+                offer: Hand::from("wool"),
+                request: Hand::from("grain")
+            })
+        );
+        // "you" is us, but "Young" isn't
+        assert_eq!(
+            event(&format!("{GUEST}Young stole {} from you", card("ore"))),
+            Some(Event::Rob {
+                robber: "Young".to_owned(),
+                victim: "Magee#8353".to_owned(),
+                card: Some(Resource::Ore)
+            })
+        );
+        // spectators aren't told who "you" is
+        let rob = message(&format!("{GUEST}Young stole {} from you", card("ore")));
+        assert_eq!(
+            rob.event(None),
+            Some(Event::RobFromUnknown {
+                robber: "Young".to_owned(),
+                card: Resource::Ore
+            })
+        );
+        assert_eq!(
+            event(&format!(
+                "{GUEST}You stole: {} from: Young",
+                card("rescardback")
+            )),
+            Some(Event::Rob {
+                robber: "Magee#8353".to_owned(),
+                victim: "Young".to_owned(),
+                card: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_known_names() {
+        let names: HashSet<String> = ["bought a house", "Ann from: Bob", "张伟", "🐸 frog.king-2"]
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        let resolve = |body: &str| {
+            let mut message = message(body);
+            message.resolve(&names);
+            message.event(Some("Magee#8353"))
+        };
+        // a name that contains a verb
+        assert_eq!(
+            resolve(&format!(
+                "{GUEST}bought a house bought {}",
+                card("devcardback")
+            )),
+            Some(Event::Purchase {
+                player: "bought a house".to_owned(),
+                item: Item::DevelopmentCard
+            })
+        );
+        // a name that contains a keyword
+        assert_eq!(
+            resolve(&format!(
+                "{GUEST}张伟 stole {} from: Ann from: Bob",
+                card("rescardback")
+            )),
+            Some(Event::Rob {
+                robber: "张伟".to_owned(),
+                victim: "Ann from: Bob".to_owned(),
+                card: None
+            })
+        );
+        assert_eq!(
+            resolve(&format!(
+                "{GUEST}🐸 frog.king-2 traded: {} for: {} with: 张伟",
+                card("wool"),
+                card("ore")
+            )),
+            Some(Event::Trade {
+                player: "🐸 frog.king-2".to_owned(),
+                counterparty: "张伟".to_owned(),
+                offer: Hand::from("wool"),
+                request: Hand::from("ore")
+            })
+        );
+        // unknown names still end at the first verb
+        assert_eq!(
+            resolve(&format!("{GUEST}Jean-Luc O'Neil got: {}", card("ore"))),
+            Some(Event::Receive {
+                player: "Jean-Luc O'Neil".to_owned(),
+                cards: Hand::from("ore")
+            })
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(message(&format!("{BOT}Meras placed a ")).kind, Kind::Event);
+        // chat that reads like an event
+        let chat = message(&format!("{}dUpp: Bob stole 5 ore", card("brick")));
+        assert_eq!(
+            chat.kind,
+            Kind::Chat(Chat {
+                player: "brickdUpp".to_owned(),
+                text: "Bob stole 5 ore".to_owned()
+            })
+        );
+        let chat = message("Magee#8353: Young stole 5 ore from: you");
+        assert_eq!(
+            chat.kind,
+            Kind::Chat(Chat {
+                player: "Magee#8353".to_owned(),
+                text: "Young stole 5 ore from: you".to_owned()
+            })
+        );
+        assert_eq!(chat.event(Some("Magee#8353")), None);
+
+        let notice = |body: &str| {
+            let html = format!(
+                r#"<div class="message_post" style="color: rgb(102, 102, 102);">{body}</div>"#
+            );
+            parse_messages(&html).remove(0).kind
+        };
+        assert_eq!(
+            notice("Karma System: Inactive. Leavers will not receive a karma penalty"),
+            Kind::Notice
+        );
+        let left = parse_messages(&format!(
+            r#"<div class="message_post" style="color: rgb(102, 102, 102);">{}dUpp has left the game</div>"#,
+            card("brick")
+        ))
+        .remove(0);
+        assert_eq!(left.kind, Kind::Notice);
+        assert_eq!(
+            left.event(None),
+            Some(Event::Left {
+                player: "brickdUpp".to_owned()
+            })
+        );
+        assert_eq!(
+            notice(
+                r#"<img src="/dist/images/prob_9.svg" alt="prob_9"> <img src="/dist/images/tile_grain.svg" alt="grain tile"> is blocked by the robber."#
+            ),
+            Kind::Notice
+        );
+    }
+
+    #[test]
+    fn test_no_event() {
+        assert_eq!(event(&format!("{BOT}No player to steal from")), None);
+        assert_eq!(
+            message(
+                r#"<img src="/dist/images/icon_trophy.svg" alt="trophy">Magee#8353 won the game!"#
+            )
+            .winner(),
+            Some("Magee#8353")
+        );
+    }
+}
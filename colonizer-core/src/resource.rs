@@ -1,14 +1,36 @@
+//! The resources of the game
+
+/// The number of resources
 pub const N_RESOURCES: usize = 5;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A resource, in the order the game lists them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Resource {
+    /// From forests
     Lumber,
+    /// From hills
     Brick,
+    /// From pastures
     Wool,
+    /// From fields
     Grain,
+    /// From mountains
     Ore,
 }
 
+impl Resource {
+    /// The name used for the resource in the game log
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Lumber => "lumber",
+            Self::Brick => "brick",
+            Self::Wool => "wool",
+            Self::Grain => "grain",
+            Self::Ore => "ore",
+        }
+    }
+}
+
 impl TryFrom<&str> for Resource {
     type Error = ();
 
@@ -47,4 +69,4 @@ impl From<Resource> for usize {
             Resource::Ore => 4,
         }
     }
-}
\ No newline at end of file
+}
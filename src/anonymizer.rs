@@ -0,0 +1,292 @@
+//! Replaces player names in a game log so it can be shared
+use crate::recorder::split_messages;
+use crate::state::State;
+use colonizer_core::message::{parse_messages, Kind, Token};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+lazy_static! {
+    /// Tags and the text between them
+    static ref TOKEN: Regex = Regex::new(r"<[^>]*>|[^<]+").unwrap();
+    static ref ALT: Regex = Regex::new(r#"alt="([^"]*)""#).unwrap();
+    static ref YOU: Regex = Regex::new(r"\b[Yy]ou\b").unwrap();
+    /// Names starting with a resource, which the log shows as the resource's icon
+    static ref RESOURCE_PREFIX: Regex = Regex::new("^(brick|lumber|wool|grain|ore)(.+)").unwrap();
+}
+
+/// An anonymized log
+pub struct Anonymized {
+    pub html: String,
+    /// The original and the new name of each player, in order of appearance
+    pub names: Vec<(String, String)>,
+    /// How many chat messages were removed
+    pub removed: usize,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Whether `c` could be part of a name, so a match next to it is part of a longer word
+fn is_name_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '#')
+}
+
+/// Replaces whole-word occurrences of `name` in `text`
+fn replace_name(text: &str, name: &str, alias: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find(name) {
+        let end = i + name.len();
+        let whole = !is_name_char(rest[..i].chars().next_back())
+            && !is_name_char(rest[end..].chars().next());
+        result.push_str(&rest[..i]);
+        result.push_str(if whole { alias } else { name });
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Whether the first and the last "you" in a message's text refer to us, as
+/// the tokenizer found them. The player a message is about comes first, and
+/// the other player of a rob or trade last.
+fn you_references(message: &str) -> (bool, bool) {
+    let Some(message) = parse_messages(message).pop() else {
+        return (false, false);
+    };
+    if message.kind != Kind::Event {
+        return (false, false);
+    }
+    let players: Vec<&Token> = message
+        .tokens
+        .iter()
+        .filter(|token| matches!(token, Token::Player { .. } | Token::You))
+        .collect();
+    (
+        players.first() == Some(&&Token::You),
+        players.len() > 1 && players.last() == Some(&&Token::You),
+    )
+}
+
+/// Rewrites the names of the players in a log consistently, including "you"
+/// references to `username`, and removes chat messages. The result replays to
+/// the same tables, under the new names.
+pub fn anonymize(html: &str, username: &str) -> Anonymized {
+    // the players are the ones the state knows about, under every name their
+    // seat went by, in the order the seats were taken
+    let mut state = State::new(username.to_owned());
+    state.update(html);
+    let mut aliases: Vec<(usize, bool, &str)> = state
+        .aliases()
+        .map(|(name, seat)| (seat, name != state.seats()[seat].id, name))
+        .collect();
+    aliases.sort();
+    let mut names: Vec<(String, String)> = Vec::new();
+    for name in std::iter::once(username).chain(aliases.into_iter().map(|(_, _, name)| name)) {
+        let name = name.to_owned();
+        if !names.iter().any(|(n, _)| *n == name) {
+            let alias = format!("Player{}", names.len() + 1);
+            names.push((name, alias));
+        }
+    }
+    // replace longer names first, in case a name contains another
+    let mut replacements: Vec<(String, String)> = names
+        .iter()
+        .map(|(name, alias)| (escape(name), alias.clone()))
+        .collect();
+    replacements.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    let own_alias = names[0].1.clone();
+    // the icon and the rest of the name of players whose name starts with a resource
+    let prefixed: Vec<(Regex, String)> = replacements
+        .iter()
+        .filter_map(|(name, alias)| {
+            let caps = RESOURCE_PREFIX.captures(name)?;
+            let pattern = format!(
+                r#"<img[^>]*alt="{}"[^>]*>\s*{}\b"#,
+                &caps[1],
+                regex::escape(&caps[2])
+            );
+            Some((Regex::new(&pattern).unwrap(), alias.clone()))
+        })
+        .collect();
+
+    let mut result = Vec::new();
+    let mut removed = 0;
+    for mut message in split_messages(html) {
+        for (pattern, alias) in &prefixed {
+            message = pattern.replace_all(&message, alias.as_str()).into_owned();
+        }
+        if parse_messages(&message)
+            .iter()
+            .any(|m| matches!(m.kind, Kind::Chat(_)))
+        {
+            removed += 1;
+            continue;
+        }
+
+        let count: usize = TOKEN
+            .find_iter(&message)
+            .map(|token| token.as_str())
+            .filter(|token| !token.starts_with('<'))
+            .map(|text| YOU.find_iter(text).count())
+            .sum();
+        let (subject, object) = you_references(&message);
+        let mut seen = 0;
+        let anonymized: String = TOKEN
+            .find_iter(&message)
+            .map(|token| {
+                let token = token.as_str();
+                if token.starts_with('<') {
+                    ALT.replace_all(token, |caps: &Captures| {
+                        let alt = replacements
+                            .iter()
+                            .find(|(name, _)| *name == caps[1])
+                            .map_or(&caps[1], |(_, alias)| alias.as_str());
+                        format!(r#"alt="{alt}""#)
+                    })
+                    .into_owned()
+                } else {
+                    // only the "you"s that refer to a player, not ones in other text
+                    let mut text = YOU
+                        .replace_all(token, |caps: &Captures| {
+                            let i = seen;
+                            seen += 1;
+                            if (i == 0 && subject) || (i + 1 == count && object) {
+                                own_alias.clone()
+                            } else {
+                                caps[0].to_owned()
+                            }
+                        })
+                        .into_owned();
+                    for (name, alias) in &replacements {
+                        text = replace_name(&text, name, alias);
+                    }
+                    text
+                }
+            })
+            .collect();
+        result.push(anonymized);
+    }
+    Anonymized {
+        html: result.join("\n"),
+        names,
+        removed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{log, sorted, USERNAME};
+
+    impl Anonymized {
+        fn alias(&self, name: &str) -> Option<&str> {
+            self.names
+                .iter()
+                .find(|(original, _)| original == name)
+                .map(|(_, alias)| alias.as_str())
+        }
+    }
+
+    #[test]
+    fn test_anonymize() {
+        for (game, username) in [("game1", USERNAME), ("game3", "fomoerotic")] {
+            let html = log(game);
+            let anonymized = anonymize(&html, username);
+            for (name, _) in &anonymized.names {
+                let name = RESOURCE_PREFIX.replace(name, "$2");
+                assert!(!anonymized.html.contains(name.as_ref()), "{name}");
+            }
+
+            let mut original = State::new(username.to_owned());
+            original.update(&html);
+            let mut replayed = State::new(anonymized.alias(username).unwrap().to_owned());
+            replayed.update(&anonymized.html);
+
+            let expected = original
+                .build_table()
+                .into_iter()
+                .map(|(name, color, cards)| {
+                    (anonymized.alias(&name).unwrap().to_owned(), color, cards)
+                })
+                .collect();
+            assert_eq!(sorted(replayed.build_table()), sorted(expected), "{game}");
+            assert_eq!(replayed.len(), original.len());
+        }
+    }
+
+    /// A message about a player in red, with a player's or a bot's avatar
+    fn red(avatar: &str, body: &str) -> String {
+        format!(
+            r#"<div class="message_post" style="color: rgb(226, 113, 116);"><img src="/dist/images/icon_{avatar}.svg" alt="{avatar}">{body}</div>"#
+        )
+    }
+
+    fn card(name: &str) -> String {
+        format!(r#"<img src="/dist/images/card_{name}.svg" alt="{name}">"#)
+    }
+
+    #[test]
+    fn test_takeover() {
+        let notice = r#"<div class="message_post" style="color: rgb(102, 102, 102);">Kim has left the game</div>"#;
+        let html = [
+            red(
+                "player",
+                &format!("Kim got: {}{}", card("ore"), card("ore")),
+            ),
+            notice.to_owned(),
+            // a bot plays the seat, then its player comes back under another name
+            red("bot", &format!("Rolf discarded: {}", card("ore"))),
+            red("player", &format!("Kimberly got: {}", card("wool"))),
+            red(
+                "player",
+                &format!("Kimberly stole {} from you", card("wool")),
+            ),
+        ]
+        .join("\n");
+        let anonymized = anonymize(&html, "Lee");
+        for name in ["Kim", "Rolf", "Kimberly", "Lee", "from you"] {
+            assert!(!anonymized.html.contains(name), "{name}");
+        }
+        assert_eq!(anonymized.names.len(), 4);
+
+        let mut original = State::new("Lee".to_owned());
+        original.update(&html);
+        let mut replayed = State::new(anonymized.alias("Lee").unwrap().to_owned());
+        replayed.update(&anonymized.html);
+        let cards = |state: &State| -> Vec<_> {
+            state
+                .build_table()
+                .into_iter()
+                .map(|(_, _, cards)| cards)
+                .collect()
+        };
+        assert_eq!(cards(&replayed), cards(&original));
+    }
+
+    #[test]
+    fn test_you() {
+        let html = [
+            red("player", &format!("You stole {} from: Kim", card("ore"))),
+            r#"<div class="message_post" style="color: rgb(102, 102, 102);">Thank you for playing</div>"#.to_owned(),
+        ]
+        .join("\n");
+        let anonymized = anonymize(&html, "Lee");
+        assert!(anonymized.html.contains("Player1 stole"));
+        // "you" that doesn't refer to a player stays
+        assert!(anonymized.html.contains("Thank you for playing"));
+    }
+
+    #[test]
+    fn test_chat() {
+        let html = r#"<div class="message_post" style="color: rgb(226, 113, 116);"><img src="/dist/images/icon_player.svg" alt="Guest">Magee#8353 placed a <img src="/dist/images/settlement_red.svg" alt="settlement"></div>
+<div class="message_post" style="color: rgb(226, 113, 116);">Magee#8353: anyone have ore?</div>"#;
+        let anonymized = anonymize(html, "Magee#8353");
+        assert_eq!(anonymized.removed, 1);
+        assert!(!anonymized.html.contains("Magee"));
+        assert!(anonymized.html.contains("Player1 placed a"));
+    }
+}
@@ -0,0 +1,467 @@
+//! Serves the tracked game as JSON on localhost, for stream overlays and
+//! scripts. The schema is documented in the README.
+//!
+//! - `GET /state`: the current `Snapshot`
+//! - `GET /events`: the `ApiEvent`s of the current game
+//! - `/ws`: a WebSocket that sends a `Push::State` when it connects and a
+//!   `Push::Event` after each event from then on
+use crate::format::best_odds;
+use crate::state::{Record, State};
+use colonizer_core::resource::{Resource, N_RESOURCES};
+use egui::Color32;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+/// A player's cards of one resource, like a cell of the table
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cards {
+    pub resource: &'static str,
+    /// The cards the player has for sure
+    pub sure: u8,
+    pub expected: f64,
+    /// The chance of getting the resource when robbing the player
+    pub rob_chance: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Player {
+    pub name: String,
+    /// e.g. `#e27174`
+    pub color: String,
+    /// Whether a bot plays the seat since its player left
+    pub bot: bool,
+    pub cards: [Cards; N_RESOURCES],
+    /// The expected size of the hand
+    pub total: f64,
+}
+
+/// The table and what goes with it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    /// 0 during the setup
+    pub turn: usize,
+    pub active_player: Option<String>,
+    pub winner: Option<String>,
+    /// The number of possible states
+    pub states: usize,
+    pub players: Vec<Player>,
+    /// The players with the best odds of robbing each resource, as the table
+    /// highlights them. Empty when nobody holds the resource. This is the only
+    /// advice colonizer gives so far.
+    pub best_rob: BTreeMap<&'static str, Vec<String>>,
+}
+
+impl Snapshot {
+    /// The current table
+    pub fn new(state: &State) -> Self {
+        Self::with_table(
+            state,
+            &state.build_table(),
+            state.len(),
+            state.current_turn(),
+        )
+    }
+
+    fn with_table(state: &State, data: &[Record], states: usize, turn: usize) -> Self {
+        let best = best_odds(data);
+        let players = data
+            .iter()
+            .map(|(name, color, cards)| Player {
+                name: name.clone(),
+                color: hex(*color),
+                bot: state.seat(name).is_some_and(|seat| seat.bot),
+                cards: std::array::from_fn(|i| Cards {
+                    resource: Resource::from(i).name(),
+                    sure: cards[i].0,
+                    expected: cards[i].1,
+                    rob_chance: cards[i].2,
+                }),
+                total: cards.iter().map(|&(_, expected, _)| expected).sum(),
+            })
+            .collect();
+        let best_rob = (0..N_RESOURCES)
+            .map(|i| {
+                let players = data
+                    .iter()
+                    .filter(|(_, _, cards)| best[i] > 0.0 && cards[i].2 == best[i])
+                    .map(|(name, _, _)| name.clone())
+                    .collect();
+                (Resource::from(i).name(), players)
+            })
+            .collect();
+        Self {
+            turn,
+            active_player: state.active_player().map(str::to_owned),
+            winner: state.winner().map(str::to_owned),
+            states,
+            players,
+            best_rob,
+        }
+    }
+}
+
+/// An event from the log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiEvent {
+    pub turn: usize,
+    /// The message as the log shows it
+    pub text: String,
+    /// What it was understood as, e.g. `Dong stole card from Brig`
+    pub event: String,
+}
+
+/// What the WebSocket sends
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Push {
+    /// When a client connects. `state` is `null` until the tracker's first poll.
+    State {
+        state: Option<Snapshot>,
+        events: Vec<ApiEvent>,
+    },
+    /// After each event, with the table after it
+    Event { event: ApiEvent, state: Snapshot },
+    /// A new game started, or the log was read again from the start, so the
+    /// events so far are gone
+    NewGame,
+}
+
+fn hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// How many pushes a WebSocket client can fall behind before it's dropped
+const BACKLOG: usize = 64;
+/// How long a write to a client can take before the client is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the connections are served from
+#[derive(Default)]
+struct Shared {
+    state: Option<Snapshot>,
+    events: Vec<ApiEvent>,
+    /// The queues of the WebSocket clients' writers
+    clients: Vec<SyncSender<String>>,
+}
+
+impl Shared {
+    /// Queues a push for every WebSocket client, dropping the ones that went
+    /// away or fell behind, so a client can't hold up the tracker
+    fn push(&mut self, push: &Push) {
+        let json = serde_json::to_string(push).unwrap();
+        self.clients
+            .retain(|client| client.try_send(json.clone()).is_ok());
+    }
+}
+
+pub struct Api {
+    shared: Arc<Mutex<Shared>>,
+    addr: SocketAddr,
+    /// The state's generation when the log was last published
+    generation: usize,
+    /// The number of log lines published
+    published: usize,
+}
+
+impl Api {
+    /// Starts serving on `port` of localhost, or a free port if it's 0
+    pub fn start(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let connections = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&connections);
+                // a client that doesn't follow the protocol only loses its connection
+                std::thread::spawn(move || handle(stream, &shared));
+            }
+        });
+        Ok(Self {
+            shared,
+            addr,
+            generation: 0,
+            published: 0,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Serves the state from now on, and pushes the events processed since
+    /// the last call
+    pub fn publish(&mut self, state: &State) {
+        let log = state.log();
+        let mut shared = self.shared.lock().unwrap();
+        // the log starts over with a new game, or when it's read again
+        if state.generation() != self.generation {
+            self.generation = state.generation();
+            self.published = 0;
+            shared.events.clear();
+            shared.push(&Push::NewGame);
+        }
+        for line in &log[self.published..] {
+            let (Some(event), Some(table)) = (&line.event, &line.table) else {
+                continue;
+            };
+            let event = ApiEvent {
+                turn: line.turn,
+                text: line.text.clone(),
+                event: event.to_string(),
+            };
+            shared.events.push(event.clone());
+            shared.push(&Push::Event {
+                event,
+                state: Snapshot::with_table(state, table, line.states, line.turn),
+            });
+        }
+        self.published = log.len();
+        shared.state = Some(Snapshot::new(state));
+    }
+}
+
+/// Answers a request, or turns the connection into a WebSocket client
+fn handle(mut stream: TcpStream, shared: &Mutex<Shared>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    // peek, so a WebSocket handshake can read the request again
+    let mut buffer = [0; 4096];
+    let n = stream.peek(&mut buffer)?;
+    let request = String::from_utf8_lossy(&buffer[..n]).to_ascii_lowercase();
+    let header = |name: &str| {
+        request
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .map(str::trim)
+    };
+    // web pages could reach us under another host name through DNS rebinding,
+    // and pages of other sites send their own origin. Programs that aren't
+    // browsers send no origin
+    let origin = header("origin:").map(|origin| {
+        origin
+            .strip_prefix("http://")
+            .or_else(|| origin.strip_prefix("https://"))
+            .unwrap_or_default()
+    });
+    if !is_local(header("host:").unwrap_or_default()) || !origin.is_none_or(is_local) {
+        return respond(&mut stream, "403 Forbidden", "null");
+    }
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+    if header("upgrade:") == Some("websocket") {
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let client = tungstenite::accept(stream).map_err(|e| io::Error::other(e.to_string()))?;
+        let (sender, receiver) = mpsc::sync_channel(BACKLOG);
+        {
+            // the hello and the pushes after it are queued under the same lock,
+            // so no event falls between them
+            let mut shared = shared.lock().unwrap();
+            let hello = Push::State {
+                state: shared.state.clone(),
+                events: shared.events.clone(),
+            };
+            sender
+                .try_send(serde_json::to_string(&hello)?)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            shared.clients.push(sender);
+        }
+        write_pushes(client, &receiver);
+        return Ok(());
+    }
+
+    stream.read_exact(&mut buffer[..n])?;
+    let shared = shared.lock().unwrap();
+    match path {
+        "/state" => respond(
+            &mut stream,
+            "200 OK",
+            &serde_json::to_string(&shared.state)?,
+        ),
+        "/events" => respond(
+            &mut stream,
+            "200 OK",
+            &serde_json::to_string(&shared.events)?,
+        ),
+        _ => respond(&mut stream, "404 Not Found", "null"),
+    }
+}
+
+/// Writes the queued pushes to a client until it goes away, or it's dropped
+/// from the clients and the queue closes
+fn write_pushes(mut client: WebSocket<TcpStream>, pushes: &Receiver<String>) {
+    for push in pushes {
+        if client.write_message(Message::Text(push)).is_err() {
+            return;
+        }
+    }
+    let _ = client.close(None);
+}
+
+/// Whether a `host[:port]` is this machine
+fn is_local(authority: &str) -> bool {
+    let host = authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host);
+    matches!(host, "localhost" | "127.0.0.1")
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{log, USERNAME};
+    use crate::recorder::split_messages;
+    use serde_json::Value;
+    use std::io::BufRead;
+    use tungstenite::client::IntoClientRequest;
+
+    fn get(api: &Api, path: &str) -> Value {
+        ureq::get(&format!("http://{}{path}", api.addr()))
+            .call()
+            .unwrap()
+            .into_json()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_api() {
+        let messages: Vec<String> = split_messages(&log("game1"));
+        let mut api = Api::start(0).unwrap();
+        assert_eq!(get(&api, "/state"), Value::Null);
+
+        let (mut client, _) = tungstenite::connect(format!("ws://{}/ws", api.addr())).unwrap();
+        let mut read = || -> Value {
+            serde_json::from_str(client.read_message().unwrap().to_text().unwrap()).unwrap()
+        };
+        assert_eq!(read()["type"], "state");
+
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&messages[..40].concat());
+        api.publish(&state);
+        let events = get(&api, "/events");
+        let events = events.as_array().unwrap();
+        assert_eq!(
+            events.len(),
+            state
+                .log()
+                .iter()
+                .filter(|line| line.event.is_some())
+                .count()
+        );
+        // a push for each event, with the table after it
+        for event in events {
+            let push = read();
+            assert_eq!(push["type"], "event");
+            assert_eq!(push["event"], *event);
+        }
+
+        let snapshot = get(&api, "/state");
+        assert_eq!(
+            snapshot,
+            serde_json::to_value(Snapshot::new(&state)).unwrap()
+        );
+        assert_eq!(snapshot["states"], state.len());
+        let players = snapshot["players"].as_array().unwrap();
+        assert_eq!(players.len(), state.build_table().len());
+        assert_eq!(players[0]["cards"][0]["resource"], "lumber");
+        assert!(matches!(
+            ureq::get(&format!("http://{}/nothing", api.addr())).call(),
+            Err(ureq::Error::Status(404, _))
+        ));
+
+        // only answers requests for localhost
+        let mut stream = TcpStream::connect(api.addr()).unwrap();
+        write!(stream, "GET /state HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        let mut status = String::new();
+        io::BufReader::new(stream).read_line(&mut status).unwrap();
+        assert!(status.contains("403"), "{status}");
+
+        // nor pages of other sites, which can't read the answers either
+        let response = ureq::get(&format!("http://{}/state", api.addr()))
+            .set("Origin", "http://localhost:8080")
+            .call()
+            .unwrap();
+        assert_eq!(response.header("Access-Control-Allow-Origin"), None);
+        assert!(matches!(
+            ureq::get(&format!("http://{}/state", api.addr()))
+                .set("Origin", "https://example.com")
+                .call(),
+            Err(ureq::Error::Status(403, _))
+        ));
+        let mut request = format!("ws://{}/ws", api.addr())
+            .into_client_request()
+            .unwrap();
+        request
+            .headers_mut()
+            .insert("Origin", "https://example.com".parse().unwrap());
+        assert!(matches!(
+            tungstenite::connect(request),
+            Err(tungstenite::Error::Http(response)) if response.status() == 403
+        ));
+    }
+
+    #[test]
+    fn test_slow_clients() {
+        // a connection that doesn't send its request doesn't hold up the others
+        let api = Api::start(0).unwrap();
+        let _idle = TcpStream::connect(api.addr()).unwrap();
+        let start = std::time::Instant::now();
+        assert_eq!(get(&api, "/state"), Value::Null);
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        // a client that stops reading is dropped once its queue is full
+        let mut shared = Shared::default();
+        let (sender, _pushes) = mpsc::sync_channel(BACKLOG);
+        shared.clients.push(sender);
+        for _ in 0..BACKLOG {
+            shared.push(&Push::NewGame);
+        }
+        assert_eq!(shared.clients.len(), 1);
+        shared.push(&Push::NewGame);
+        assert!(shared.clients.is_empty());
+    }
+
+    #[test]
+    fn test_start_over() {
+        let game1 = split_messages(&log("game1"));
+        let game3 = split_messages(&log("game3"));
+        let mut api = Api::start(0).unwrap();
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&game1[..100].concat());
+        api.publish(&state);
+        state.update(&game1[40..140].concat());
+        api.publish(&state);
+
+        // a log that doesn't line up is read again from the start, into a shorter log
+        let (mut client, _) = tungstenite::connect(format!("ws://{}/ws", api.addr())).unwrap();
+        client.read_message().unwrap();
+        state.update(&game3[..110].concat());
+        assert!(state.archive().is_empty());
+        assert!(state.log().len() < 140);
+        api.publish(&state);
+        let push: Value =
+            serde_json::from_str(client.read_message().unwrap().to_text().unwrap()).unwrap();
+        assert_eq!(push["type"], "new_game");
+        assert_eq!(
+            get(&api, "/events").as_array().unwrap().len(),
+            state
+                .log()
+                .iter()
+                .filter(|line| line.event.is_some())
+                .count()
+        );
+    }
+}
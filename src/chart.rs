@@ -0,0 +1,169 @@
+//! How the players' hands changed over the game, from the table after each event
+use crate::state::{LogLine, State};
+use colonizer_core::event::{Event, Item};
+use colonizer_core::resource::N_RESOURCES;
+use egui::Color32;
+
+/// Something that happened to a player, marked on their timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Rob,
+    Trade,
+    Build,
+    /// A 7 was rolled, so everyone with too many cards discards
+    Seven,
+}
+
+impl Marker {
+    pub const ALL: [Self; 4] = [Self::Rob, Self::Trade, Self::Build, Self::Seven];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Rob => "Rob",
+            Self::Trade => "Trade",
+            Self::Build => "Build",
+            Self::Seven => "7",
+        }
+    }
+}
+
+/// The points of one player's chart, by turn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline {
+    /// The name the events use for the player's seat
+    pub id: String,
+    /// The name the seat goes by now
+    pub name: String,
+    pub color: Color32,
+    /// The expected count of each resource
+    pub resources: [Vec<[f64; 2]>; N_RESOURCES],
+    /// The expected hand size
+    pub total: Vec<[f64; 2]>,
+    /// The events involving the player, on the hand size line
+    pub markers: Vec<(Marker, [f64; 2])>,
+}
+
+/// The markers for an event, with the player each is for, or `None` for everyone
+fn markers(event: &Event) -> Vec<(Marker, Option<&str>)> {
+    match event {
+        Event::Rob { robber, victim, .. } => {
+            vec![(Marker::Rob, Some(robber)), (Marker::Rob, Some(victim))]
+        }
+        Event::RobFromUnknown { robber, .. } => vec![(Marker::Rob, Some(robber))],
+        Event::Trade {
+            player,
+            counterparty,
+            ..
+        } => vec![
+            (Marker::Trade, Some(player)),
+            (Marker::Trade, Some(counterparty)),
+        ],
+        Event::BankTrade { player, .. } => vec![(Marker::Trade, Some(player))],
+        Event::Purchase { player, item } if *item != Item::DevelopmentCard => {
+            vec![(Marker::Build, Some(player))]
+        }
+        Event::Roll { dice, .. } if dice[0] + dice[1] == 7 => vec![(Marker::Seven, None)],
+        _ => Vec::new(),
+    }
+}
+
+/// Where each log line falls on the time axis: its turn, plus how far into
+/// the turn it came
+fn times(log: &[LogLine]) -> Vec<f64> {
+    let mut times = Vec::with_capacity(log.len());
+    for turn in log.chunk_by(|a, b| a.turn == b.turn) {
+        let n = turn.len() as f64;
+        times.extend((0..turn.len()).map(|i| turn[0].turn as f64 + i as f64 / n));
+    }
+    times
+}
+
+/// The timelines of the players, in the order they were first seen
+pub fn timelines(state: &State) -> Vec<Timeline> {
+    let log = state.log();
+    let mut timelines: Vec<Timeline> = Vec::new();
+    // only events change the hands
+    for (line, x) in log.iter().zip(times(log)) {
+        let (Some(event), Some(table)) = (&line.event, &line.table) else {
+            continue;
+        };
+        let markers = markers(event);
+        for (name, _, cards) in table {
+            let Some(seat) = state.seat(name) else {
+                continue;
+            };
+            let i = match timelines.iter().position(|t| t.id == seat.id) {
+                Some(i) => i,
+                None => {
+                    timelines.push(Timeline {
+                        id: seat.id.clone(),
+                        name: seat.name.clone(),
+                        color: seat.color.unwrap_or(Color32::WHITE),
+                        resources: Default::default(),
+                        total: Vec::new(),
+                        markers: Vec::new(),
+                    });
+                    timelines.len() - 1
+                }
+            };
+            let timeline = &mut timelines[i];
+            for (points, &(_, expected, _)) in timeline.resources.iter_mut().zip(cards) {
+                points.push([x, expected]);
+            }
+            let total = cards.iter().map(|&(_, expected, _)| expected).sum();
+            timeline.total.push([x, total]);
+            for &(marker, player) in &markers {
+                if player.is_none_or(|player| state.seat(player) == Some(seat)) {
+                    timeline.markers.push((marker, [x, total]));
+                }
+            }
+        }
+    }
+    timelines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::replay;
+
+    #[test]
+    fn test_timelines() {
+        let state = replay("game1");
+        let timelines = timelines(&state);
+        assert_eq!(timelines.len(), 4);
+
+        for (name, _, cards) in state.build_table() {
+            let timeline = timelines.iter().find(|t| t.name == name).unwrap();
+            let total: f64 = cards.iter().map(|&(_, expected, _)| expected).sum();
+            assert_eq!(timeline.total.last().unwrap()[1], total);
+            for (points, (_, expected, _)) in timeline.resources.iter().zip(cards) {
+                assert_eq!(points.last().unwrap()[1], expected);
+            }
+            // the points go forward in time
+            assert!(timeline.total.windows(2).all(|w| w[0][0] < w[1][0]));
+        }
+
+        // everyone gets a marker for each 7
+        let sevens = state
+            .log()
+            .iter()
+            .filter(|line| matches!(line.event, Some(Event::Roll { dice, .. }) if dice[0] + dice[1] == 7))
+            .count();
+        assert!(sevens > 0);
+        for timeline in &timelines {
+            let count = |marker| {
+                timeline
+                    .markers
+                    .iter()
+                    .filter(|(m, _)| *m == marker)
+                    .count()
+            };
+            assert_eq!(count(Marker::Seven), sevens);
+            assert!(count(Marker::Build) > 0);
+        }
+        assert!(timelines
+            .iter()
+            .any(|t| t.markers.iter().any(|(m, _)| *m == Marker::Rob)));
+    }
+}
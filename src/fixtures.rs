@@ -0,0 +1,29 @@
+//! The recorded games the tests replay, and how they compare the results
+use crate::state::{Record, State};
+
+/// Whose view the recorded games are from, apart from game3
+pub const USERNAME: &str = "Magee#8353";
+
+/// The log of a recorded game, e.g. `game1`
+pub fn log(game: &str) -> String {
+    std::fs::read_to_string(format!("games/{game}.html")).unwrap()
+}
+
+/// A recorded game read in one go, from `USERNAME`'s view
+pub fn replay(game: &str) -> State {
+    let mut state = State::new(USERNAME.to_owned());
+    state.update(&log(game));
+    state
+}
+
+/// The table in a fixed order, since players are only sorted by turn order
+pub fn sorted(mut table: Vec<Record>) -> Vec<Record> {
+    table.sort_by(|a, b| a.0.cmp(&b.0));
+    table
+}
+
+/// Asserts that two states ended up with the same table
+#[track_caller]
+pub fn assert_same_table(actual: &State, expected: &State) {
+    assert_eq!(sorted(actual.build_table()), sorted(expected.build_table()));
+}
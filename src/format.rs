@@ -0,0 +1,104 @@
+//! Formatting of the table shared by the presenters
+use crate::state::{Record, State};
+use colonizer_core::event::Event;
+use colonizer_core::resource::N_RESOURCES;
+use egui::Color32;
+
+/// The column headers and their colors
+pub const RESOURCE_COLUMNS: [(&str, Color32); N_RESOURCES] = [
+    ("Lumber", Color32::from_rgb(95, 185, 60)),
+    ("Brick", Color32::from_rgb(185, 100, 90)),
+    ("Wool", Color32::from_rgb(140, 200, 60)),
+    ("Grain", Color32::from_rgb(210, 150, 70)),
+    ("Ore", Color32::from_rgb(140, 175, 160)),
+];
+
+/// Highlights the players with the best odds of robbing a resource
+pub const BEST_ODDS: Color32 = Color32::from_rgb(95, 185, 60);
+
+/// The background of the name of the player whose turn it is
+pub const ACTIVE_PLAYER: Color32 = Color32::from_gray(70);
+
+/// The background of the log lines no pattern matched
+pub const UNPARSED: Color32 = Color32::from_rgb(110, 40, 40);
+
+// Formats the rob chance as a probability into a percentage
+pub fn fmt_rob_chance(rob_chance: f64) -> String {
+    let percentage = (rob_chance * 100.0).round() as u8;
+    if percentage == 100 {
+        // pretty easy to see that it's the only card available
+        "   ".to_owned()
+    } else if percentage < 10 {
+        format!(".0{percentage:<1}")
+    } else {
+        format!(
+            ".{:<2}",
+            if percentage.is_multiple_of(10) {
+                percentage / 10
+            } else {
+                percentage
+            }
+        )
+    }
+}
+
+/// custom formatting based on the info that needs to be displayed
+/// each field should be 11 chars long
+pub fn fmt_resource(sure: u8, expected: f64, rob_chance: f64) -> String {
+    let chance = fmt_rob_chance(rob_chance);
+    let unsure = expected - f64::from(sure);
+
+    if rob_chance == 0.0 {
+        "           ".to_owned()
+    } else if unsure == 0.0 {
+        format!("{chance} {sure:>2}     ")
+    } else if unsure < 1.0 {
+        format!("{chance} {:>5.2}  ", f64::from(sure) + unsure)
+    } else {
+        format!("{chance} {sure:>2}+{unsure:>4.2}")
+    }
+}
+
+/// The best odds of getting each resource by robbing someone
+pub fn best_odds(data: &[Record]) -> [f64; N_RESOURCES] {
+    let mut best = [0.0f64; N_RESOURCES];
+    for (_, _, cards) in data {
+        for (i, &(_, _, rob_chance)) in cards.iter().enumerate() {
+            best[i] = best[i].max(rob_chance);
+        }
+    }
+    best
+}
+
+/// Describes the current turn, e.g. `Turn 12: Dong rolled 8`
+pub fn fmt_turn(state: &State) -> String {
+    let turns = state.turns();
+    match turns.last().and_then(|turn| turn.events.first()) {
+        Some(Event::Roll { player, dice }) => format!(
+            "Turn {}: {} rolled {}",
+            turns.len() - 1,
+            state.display_name(player),
+            dice[0] + dice[1]
+        ),
+        _ => "Setup".to_owned(),
+    }
+}
+
+/// Says whose view we're watching from, when spectating
+pub fn fmt_perspective(state: &State) -> Option<String> {
+    if !state.spectating() {
+        return None;
+    }
+    Some(match state.perspective() {
+        Some(player) => format!("Spectating from {}'s view", state.display_name(player)),
+        None => "Spectating, robs involving the page's player are skipped".to_owned(),
+    })
+}
+
+/// A player's name, marked when a bot plays their seat since they left
+pub fn fmt_player(state: &State, name: &str) -> String {
+    match state.seat(name) {
+        Some(seat) if seat.bot => format!("{name} (bot)"),
+        _ => name.to_owned(),
+    }
+}
@@ -1,17 +1,145 @@
+use api::Api;
+use cdp_client::TargetFilter;
+use chart::{Marker, Timeline};
 use eframe::{egui, NativeOptions};
+use egui::plot::{Legend, Line, MarkerShape, Plot, Points};
 use egui::{Color32, RichText};
-use resource::N_RESOURCES;
-use state::State;
-mod card_tracker;
-mod hand;
-mod html_parser;
-mod resource;
+use format::{fmt_player, fmt_resource, ACTIVE_PLAYER, RESOURCE_COLUMNS, UNPARSED};
+use options::{Options, USAGE};
+use overlay::Overlay;
+use presenter::{Input, Presenter, View};
+use recorder::{Recorder, Recording};
+use std::path::Path;
+use tracker::{Tracker, GAME_URL};
+use tui::Tui;
+mod anonymizer;
+mod api;
+mod chart;
+#[cfg(test)]
+mod fixtures;
+mod format;
+mod options;
+mod overlay;
+mod presenter;
+mod recorder;
 mod state;
+mod tracker;
+mod tui;
+
+fn list_tabs(options: &Options) -> Result<(), cdp_client::Error> {
+    let targets = cdp_client::targets(&options.debugger_url())?;
+    for (i, target) in TargetFilter::page(GAME_URL)
+        .candidates(&targets)
+        .iter()
+        .enumerate()
+    {
+        println!("{i}: {} ({})", target.title, target.url);
+    }
+    Ok(())
+}
+
+/// Prints the table at the end of a recorded game
+fn replay(dir: &Path) -> std::io::Result<()> {
+    let state = Recording::load(dir)?.replay();
+    let header = RESOURCE_COLUMNS.map(|(name, _)| format!("{name:<11}"));
+    println!("{:<20} {}", "Player", header.join(" "));
+    for (name, _, cards) in state.build_table() {
+        let cards =
+            cards.map(|(sure, expected, rob_chance)| fmt_resource(sure, expected, rob_chance));
+        // mark whose turn it was
+        let marker = if state.active_player() == Some(&name) {
+            '*'
+        } else {
+            ' '
+        };
+        println!(
+            "{marker}{:<19} {}",
+            fmt_player(&state, &name),
+            cards.join(" ")
+        );
+    }
+    println!("{} turns", state.current_turn());
+    if let Some(winner) = state.winner() {
+        println!("{winner} won the game");
+    }
+    Ok(())
+}
+
+/// Anonymizes a log, the full log of a recording or a fixture
+fn anonymize(input: &Path, output: &Path, username: &str) -> std::io::Result<()> {
+    let anonymized = anonymizer::anonymize(&std::fs::read_to_string(input)?, username);
+    std::fs::write(output, &anonymized.html)?;
+    for (name, alias) in &anonymized.names {
+        println!("{name} -> {alias}");
+    }
+    println!("Removed {} chat messages", anonymized.removed);
+    Ok(())
+}
 
 fn main() {
-    let username: String = std::env::args()
-        .nth(1)
-        .expect("Please provide your colonist.io username as the first argument");
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        std::process::exit(1);
+    });
+    if options.list_tabs {
+        if let Err(e) = list_tabs(&options) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(dir) = &options.replay {
+        if let Err(e) = replay(dir) {
+            eprintln!("Unable to replay {}: {e}", dir.display());
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some((input, output)) = &options.anonymize {
+        if let Err(e) = anonymize(input, output, &options.username) {
+            eprintln!("Unable to anonymize {}: {e}", input.display());
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut tracker = Tracker::new(&options);
+    if let Some(dir) = &options.record {
+        match Recorder::create(dir, &options.username) {
+            Ok(recorder) => tracker.set_recorder(recorder),
+            Err(e) => {
+                eprintln!("Unable to record to {}: {e}", dir.display());
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(port) = options.api {
+        match Api::start(port) {
+            Ok(api) => {
+                eprintln!("Serving the API on http://{}", api.addr());
+                tracker.set_api(api);
+            }
+            Err(e) => {
+                eprintln!("Unable to serve the API on port {port}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if options.overlay {
+        presenter::run(tracker, &mut Overlay::default());
+        return;
+    }
+    if options.tui {
+        match Tui::new() {
+            Ok(mut tui) => presenter::run(tracker, &mut tui),
+            Err(e) => {
+                eprintln!("Unable to use the terminal: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     eframe::run_native(
         "Colonizer",
@@ -20,124 +148,277 @@ fn main() {
             initial_window_size: Some(egui::Vec2::new(560.0, 140.0)),
             ..Default::default()
         },
-        Box::new(|_cc| Box::new(MyApp::new(username))),
+        Box::new(move |_cc| Box::new(MyApp::new(tracker))),
     );
 }
 
 struct MyApp {
-    state: State,
-    last_update: std::time::Instant,
-    cdp: cdp_client::Browser,
+    tracker: Tracker,
+    /// Why the last poll failed, shown until a poll succeeds
+    error: Option<cdp_client::Error>,
+    /// Whether the log pane is open
+    show_log: bool,
+    /// The log line whose table is shown instead of the current one
+    selected: Option<usize>,
+    /// Whether the chart window is open
+    show_charts: bool,
+    /// The seat whose chart is shown
+    charted: Option<String>,
 }
 
 impl MyApp {
-    fn new(username: String) -> Self {
-        let browser = cdp_client::Browser::new("http://localhost:9222/json")
-            .expect("Unable to connect to Chrome");
+    fn new(tracker: Tracker) -> Self {
         Self {
-            state: State::new(username),
-            last_update: std::time::Instant::now() - std::time::Duration::from_secs(1),
-            cdp: browser,
+            tracker,
+            error: None,
+            show_log: false,
+            selected: None,
+            show_charts: false,
+            charted: None,
         }
     }
 }
 
-// Formats the rob chance as a probability into a percentage
-fn fmt_rob_chance(rob_chance: f64) -> String {
-    let percentage = (rob_chance * 100.0).round() as u8;
-    if percentage == 100 {
-        // pretty easy to see that it's the only card available
-        "   ".to_owned()
-    } else if percentage < 10 {
-        format!(".0{percentage:<1}")
-    } else {
-        format!(
-            ".{:<2}",
-            if percentage % 10 == 0 {
-                percentage / 10
-            } else {
-                percentage
+/// Shows the table of each player's cards, with the number of possible states
+fn show_table(ui: &mut egui::Ui, view: &View) {
+    egui::Grid::new("id1").striped(true).show(ui, |ui| {
+        ui.label(egui::RichText::new("Player").color(Color32::LIGHT_BLUE));
+        for (name, color) in RESOURCE_COLUMNS {
+            ui.label(egui::RichText::new(name).color(color));
+        }
+        ui.label("Total");
+        ui.end_row();
+
+        for row in &view.rows {
+            let mut label = egui::RichText::new(&row.name).color(row.color);
+            if row.active {
+                label = label.background_color(ACTIVE_PLAYER);
             }
-        )
-    }
+            ui.label(label);
+            for cell in &row.cells {
+                ui.label(RichText::new(&cell.text).color(cell.color));
+            }
+            ui.label(format!("{:>5.2}", row.total));
+            ui.end_row();
+        }
+        ui.label("Totals:");
+        for total in &view.totals {
+            ui.label(format!("{total:>5.2}"));
+        }
+        ui.label(format!("{:>5}", view.states));
+    });
 }
 
-/// custom formatting based on the info that needs to be displayed
-/// each field should be 11 chars long
-fn fmt_resource(sure: u8, expected: f64, rob_chance: f64) -> String {
-    let chance = fmt_rob_chance(rob_chance);
-    let unsure = expected - f64::from(sure);
-
-    if rob_chance == 0.0 {
-        "           ".to_owned()
-    } else if unsure == 0.0 {
-        format!("{chance} {sure:>2}     ")
-    } else if unsure < 1.0 {
-        format!("{chance} {:>5.2}  ", f64::from(sure) + unsure)
-    } else {
-        format!("{chance} {sure:>2}+{unsure:>4.2}")
+/// The window's central panel
+impl Presenter for egui::Ui {
+    fn present(
+        &mut self,
+        view: &View,
+        _tracker: &mut Tracker,
+    ) -> Result<Option<Input>, cdp_client::Error> {
+        show_table(self, view);
+        self.label(&view.turn);
+        let mut input = None;
+        if let Some(perspective) = &view.perspective {
+            self.horizontal(|ui| {
+                ui.label(perspective);
+                // the log doesn't always give away whose view it is
+                let mut selected = view.viewed.clone();
+                egui::ComboBox::from_id_source("perspective")
+                    .selected_text("Switch view")
+                    .show_ui(ui, |ui| {
+                        for (id, name) in &view.seats {
+                            ui.selectable_value(&mut selected, Some(id.clone()), name);
+                        }
+                    });
+                if selected != view.viewed {
+                    input = Some(Input::Perspective(selected));
+                }
+            });
+        }
+        for warning in view.error.iter().chain(&view.warnings) {
+            self.label(RichText::new(warning).color(Color32::LIGHT_RED));
+        }
+        Ok(input)
     }
 }
 
+/// Plots a player's expected cards of each resource and hand size, with the
+/// events that changed them
+fn show_timeline(ui: &mut egui::Ui, timeline: &Timeline) {
+    Plot::new("timeline")
+        .legend(Legend::default())
+        .include_y(0.0)
+        .show(ui, |plot| {
+            for ((name, color), points) in RESOURCE_COLUMNS.iter().zip(&timeline.resources) {
+                plot.line(Line::new(points.clone()).color(*color).name(name));
+            }
+            plot.line(
+                Line::new(timeline.total.clone())
+                    .color(timeline.color)
+                    .width(2.0)
+                    .name("Total"),
+            );
+            for marker in Marker::ALL {
+                let points: Vec<[f64; 2]> = timeline
+                    .markers
+                    .iter()
+                    .filter(|(m, _)| *m == marker)
+                    .map(|&(_, point)| point)
+                    .collect();
+                let shape = match marker {
+                    Marker::Rob => MarkerShape::Diamond,
+                    Marker::Trade => MarkerShape::Square,
+                    Marker::Build => MarkerShape::Up,
+                    Marker::Seven => MarkerShape::Asterisk,
+                };
+                plot.points(
+                    Points::new(points)
+                        .shape(shape)
+                        .radius(4.0)
+                        .color(Color32::WHITE)
+                        .name(marker.name()),
+                );
+            }
+        });
+}
+
 impl eframe::App for MyApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.tracker.finish();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.last_update.elapsed() > std::time::Duration::from_secs(1) {
-            let html = self
-                .cdp
-                .evaluate(r#"document.getElementById("game-log-text").innerHTML"#)
-                .expect("Unable to read game log");
-            // let html = std::fs::read_to_string("games/game3.html").unwrap();
-            self.state.update(&html);
+        // keep showing the last table while the browser is unreachable
+        match self.tracker.poll() {
+            Ok(()) => self.error = None,
+            Err(e) => self.error = Some(e),
+        }
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+
+        let chat = self.tracker.state.chat();
+        if !chat.is_empty() {
+            egui::TopBottomPanel::bottom("chat")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical()
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            for message in chat {
+                                let color = self.tracker.state.color(&message.player);
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label(
+                                        RichText::new(format!("{}:", message.player))
+                                            .color(color.unwrap_or(Color32::LIGHT_BLUE)),
+                                    );
+                                    ui.label(&message.text);
+                                });
+                            }
+                        });
+                });
+        }
+
+        if self.show_log {
+            egui::SidePanel::right("log")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical()
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            for (i, line) in self.tracker.state.log().iter().enumerate() {
+                                let mut text =
+                                    RichText::new(format!("{:>5} {}", line.states, line.text));
+                                if line.unparsed() {
+                                    text = text.background_color(UNPARSED);
+                                }
+                                // clicking a line shows the table after it
+                                let selected = self.selected == Some(i);
+                                if ui.selectable_label(selected, text).clicked() {
+                                    self.selected = (!selected).then_some(i);
+                                }
+                                if let Some(event) = &line.event {
+                                    ui.label(RichText::new(format!("      {event}")).weak());
+                                }
+                            }
+                        });
+                });
+        }
+
+        if self.show_charts {
+            let timelines = chart::timelines(&self.tracker.state);
+            let charted = &mut self.charted;
+            egui::Window::new("Hands")
+                .open(&mut self.show_charts)
+                .default_size([480.0, 320.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        for timeline in &timelines {
+                            ui.selectable_value(
+                                charted,
+                                Some(timeline.id.clone()),
+                                RichText::new(&timeline.name).color(timeline.color),
+                            );
+                        }
+                    });
+                    let timeline = timelines
+                        .iter()
+                        .find(|t| charted.as_ref() == Some(&t.id))
+                        .or(timelines.first());
+                    match timeline {
+                        Some(timeline) => show_timeline(ui, timeline),
+                        None => {
+                            ui.label("No events yet");
+                        }
+                    }
+                });
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
-            egui::Grid::new("id1").striped(true).show(ui, |ui| {
-                ui.label(egui::RichText::new("Player").color(Color32::LIGHT_BLUE));
-                ui.label(egui::RichText::new("Lumber").color(Color32::from_rgb(95, 185, 60)));
-                ui.label(egui::RichText::new("Brick").color(Color32::from_rgb(185, 100, 90)));
-                ui.label(egui::RichText::new("Wool").color(Color32::from_rgb(140, 200, 60)));
-                ui.label(egui::RichText::new("Grain").color(Color32::from_rgb(210, 150, 70)));
-                ui.label(egui::RichText::new("Ore").color(Color32::from_rgb(140, 175, 160)));
-                ui.label("Total");
-                ui.end_row();
-
-                let data = self.state.build_table();
-
-                // compute the best odds of getting each resource
-                let mut best = [0.0f64; N_RESOURCES];
-                for (_, _, cards) in &data {
-                    for (i, &(_, _, rob_chance)) in cards.iter().enumerate() {
-                        best[i] = best[i].max(rob_chance);
-                    }
+            let state = &self.tracker.state;
+            let selected = self.selected.and_then(|i| Some((i, state.log().get(i)?)));
+            let line = selected.map(|(_, line)| line);
+            let view = match selected {
+                Some((i, line)) => {
+                    View::with_table(state, state.table_at(i), line.states, self.error.as_ref())
                 }
-                // running totals of each resource type
-                let mut resource_totals = [0.0; N_RESOURCES];
-                for (name, color, cards) in data {
-                    ui.label(egui::RichText::new(name).color(color));
-                    let mut player_total: f64 = 0.0;
-                    for (i, &(sure, expected, rob_chance)) in cards.iter().enumerate() {
-                        player_total += expected; // row wise
-                        resource_totals[i] += expected; // column wise
-                        let color = if rob_chance == best[i] {
-                            Color32::from_rgb(95, 185, 60)
-                        } else {
-                            Color32::WHITE
-                        };
-
-                        ui.label(
-                            RichText::new(fmt_resource(sure, expected, rob_chance)).color(color),
-                        );
+                None => View::new(state, self.error.as_ref()),
+            };
+            let after = line.map(|line| format!("After: {}", line.text));
+            match ui.present(&view, &mut self.tracker) {
+                Ok(Some(input)) => presenter::handle(&mut self.tracker, input),
+                Ok(None) => {}
+                Err(e) => eprintln!("Unable to show the table: {e}"),
+            }
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut self.show_log, "Log");
+                ui.toggle_value(&mut self.show_charts, "Charts");
+                if let Some(after) = after {
+                    ui.label(after);
+                    if ui.button("Back to now").clicked() {
+                        self.selected = None;
                     }
-                    ui.label(format!("{player_total:>5.2}"));
-                    ui.end_row();
                 }
-                ui.label("Totals:");
-                for total in &resource_totals {
-                    ui.label(format!("{total:>5.2}"));
-                }
-                ui.label(format!("{:>5}", self.state.len()));
             });
+            let archive = self.tracker.state.archive();
+            if !archive.is_empty() {
+                egui::CollapsingHeader::new("Earlier games").show(ui, |ui| {
+                    for (i, game) in archive.iter().enumerate() {
+                        let winner = game.winner.as_deref().unwrap_or("nobody");
+                        ui.label(format!(
+                            "Game {}: {winner} won after {} turns",
+                            i + 1,
+                            game.turns
+                        ));
+                        for (name, color, cards) in &game.table {
+                            let total: f64 = cards.iter().map(|&(_, expected, _)| expected).sum();
+                            ui.label(
+                                RichText::new(format!("  {name}: {total:.2} cards")).color(*color),
+                            );
+                        }
+                    }
+                });
+            }
         });
     }
 }
@@ -0,0 +1,144 @@
+//! Command line options
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: colonizer [OPTIONS] <username>
+       colonizer [OPTIONS] --spectate
+
+Options:
+    --debugger <host:port>  Chrome's remote debugger [default: localhost:9222]
+    --tab <n>               Which of the open colonist.io tabs to track [default: 0]
+    --list-tabs             Print the open colonist.io tabs and exit
+    --spectate              Watch a game we aren't playing in, inferring whose view the page shows
+    --overlay               Show the table inside the colonist.io tab instead of a window
+    --tui                   Show the table in the terminal instead of a window
+    --api <port>            Serve the table and events as JSON on localhost:<port>
+    --record <dir>          Save the game log to <dir> as it's played
+    --replay <dir>          Replay a recorded game, print the table and exit
+    --anonymize <in> <out>  Replace the player names in the log <in>, remove chat and save it to <out>";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Our colonist.io username
+    pub username: String,
+    pub debugger: String,
+    pub tab: usize,
+    pub list_tabs: bool,
+    /// Watch a game without a username of our own
+    pub spectate: bool,
+    pub overlay: bool,
+    /// Show the table in the terminal
+    pub tui: bool,
+    /// The port to serve the API on
+    pub api: Option<u16>,
+    /// Where to record the game log
+    pub record: Option<PathBuf>,
+    /// A recording to replay instead of following the browser
+    pub replay: Option<PathBuf>,
+    /// A log to anonymize and where to save the result
+    pub anonymize: Option<(PathBuf, PathBuf)>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            username: String::new(),
+            debugger: "localhost:9222".to_owned(),
+            tab: 0,
+            list_tabs: false,
+            spectate: false,
+            overlay: false,
+            tui: false,
+            api: None,
+            record: None,
+            replay: None,
+            anonymize: None,
+        }
+    }
+}
+
+impl Options {
+    /// Parses the arguments, not including the program name
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut username = None;
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+            match arg.as_str() {
+                "--debugger" => options.debugger = value("--debugger")?,
+                "--tab" => {
+                    options.tab = value("--tab")?
+                        .parse()
+                        .map_err(|_| "--tab needs a number".to_owned())?;
+                }
+                "--list-tabs" => options.list_tabs = true,
+                "--spectate" => options.spectate = true,
+                "--overlay" => options.overlay = true,
+                "--tui" => options.tui = true,
+                "--api" => {
+                    options.api = Some(
+                        value("--api")?
+                            .parse()
+                            .map_err(|_| "--api needs a port".to_owned())?,
+                    );
+                }
+                "--record" => options.record = Some(value("--record")?.into()),
+                "--replay" => options.replay = Some(value("--replay")?.into()),
+                "--anonymize" => {
+                    let input = value("--anonymize")?.into();
+                    options.anonymize = Some((input, value("--anonymize")?.into()));
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+                _ => username = Some(arg),
+            }
+        }
+        if options.overlay && options.tui {
+            return Err("--overlay and --tui can't be combined".to_owned());
+        }
+        match username {
+            Some(username) => options.username = username,
+            // listing the tabs doesn't need a username, and recordings have their own
+            None if options.list_tabs || options.replay.is_some() => {}
+            // anonymizing still needs to know who "you" is
+            None if options.spectate && options.anonymize.is_none() => {}
+            None => return Err("Please provide your colonist.io username".to_owned()),
+        }
+        Ok(options)
+    }
+
+    /// The url of the debugger's list of targets
+    pub fn debugger_url(&self) -> String {
+        format!("http://{}/json", self.debugger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| (*arg).to_owned()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = parse(&["--debugger", "127.0.0.1:9333", "Magee#8353", "--tab", "1"]).unwrap();
+        assert_eq!(options.username, "Magee#8353");
+        assert_eq!(options.debugger_url(), "http://127.0.0.1:9333/json");
+        assert_eq!(options.tab, 1);
+
+        assert!(parse(&["--list-tabs"]).is_ok());
+        assert!(parse(&["--spectate"]).unwrap().spectate);
+        assert!(parse(&["--spectate", "--anonymize", "in.html", "out.html"]).is_err());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["Magee#8353", "--tab"]).is_err());
+        assert!(parse(&["Magee#8353", "--verbose"]).is_err());
+        assert!(parse(&["Magee#8353", "--tui"]).unwrap().tui);
+        assert!(parse(&["Magee#8353", "--tui", "--overlay"]).is_err());
+        assert_eq!(
+            parse(&["Magee#8353", "--api", "8765"]).unwrap().api,
+            Some(8765)
+        );
+        assert!(parse(&["Magee#8353", "--api", "port"]).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! Shows the table inside the colonist.io tab instead of a separate window
+use crate::format::{ACTIVE_PLAYER, RESOURCE_COLUMNS};
+use crate::presenter::{Input, Presenter, View};
+use crate::tracker::Tracker;
+use cdp_client::Error;
+use egui::Color32;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// A function that creates the overlay if the page doesn't have it, e.g. after
+/// a reload, and replaces its content with the html it's called with. The
+/// header drags the overlay around and its button collapses it.
+const RENDER: &str = r#"(html => {
+    let overlay = document.getElementById("colonizer-overlay");
+    if (!overlay) {
+        overlay = document.createElement("div");
+        overlay.id = "colonizer-overlay";
+        overlay.innerHTML = `<style>
+            #colonizer-overlay {
+                position: fixed; top: 8px; right: 8px; z-index: 100000;
+                background: rgba(27, 27, 27, 0.9); color: white; border-radius: 6px;
+                font: 12px monospace; box-shadow: 0 2px 8px rgba(0, 0, 0, 0.5);
+            }
+            #colonizer-overlay .colonizer-header {
+                display: flex; justify-content: space-between; align-items: center;
+                padding: 2px 6px; cursor: move; user-select: none; color: lightblue;
+            }
+            #colonizer-overlay button {
+                background: none; border: none; color: inherit; cursor: pointer; font: inherit;
+            }
+            #colonizer-overlay.collapsed .colonizer-body { display: none; }
+            #colonizer-overlay .colonizer-body { padding: 0 6px 6px; }
+            #colonizer-overlay td, #colonizer-overlay th { white-space: pre; padding: 1px 4px; text-align: left; }
+            #colonizer-overlay tr:nth-child(even) { background: rgba(255, 255, 255, 0.05); }
+            #colonizer-overlay .colonizer-warning { color: #ff8080; }
+        </style>
+        <div class="colonizer-header"><span>Colonizer</span><button>&minus;</button></div>
+        <div class="colonizer-body"></div>`;
+        document.body.appendChild(overlay);
+
+        const header = overlay.querySelector(".colonizer-header");
+        const button = header.querySelector("button");
+        button.onclick = () => {
+            overlay.classList.toggle("collapsed");
+            button.innerHTML = overlay.classList.contains("collapsed") ? "+" : "&minus;";
+        };
+        header.onmousedown = down => {
+            if (down.target === button) {
+                return;
+            }
+            down.preventDefault();
+            const x = down.clientX - overlay.offsetLeft;
+            const y = down.clientY - overlay.offsetTop;
+            const move = e => {
+                overlay.style.left = `${e.clientX - x}px`;
+                overlay.style.top = `${e.clientY - y}px`;
+                overlay.style.right = "auto";
+            };
+            const up = () => {
+                document.removeEventListener("mousemove", move);
+                document.removeEventListener("mouseup", up);
+            };
+            document.addEventListener("mousemove", move);
+            document.addEventListener("mouseup", up);
+        };
+    }
+    overlay.querySelector(".colonizer-body").innerHTML = html;
+    return "";
+})"#;
+
+/// How often to render even if nothing changed, so the overlay comes back
+/// after the page reloads
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct Overlay {
+    /// The html last rendered into the page
+    shown: String,
+    last_render: Instant,
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self {
+            shown: String::new(),
+            last_render: Instant::now() - REFRESH_INTERVAL,
+        }
+    }
+}
+
+impl Presenter for Overlay {
+    /// Renders the table into the page if it changed since the last call
+    fn present(&mut self, view: &View, tracker: &mut Tracker) -> Result<Option<Input>, Error> {
+        let html = render(view);
+        if html == self.shown && self.last_render.elapsed() < REFRESH_INTERVAL {
+            return Ok(None);
+        }
+        let argument = serde_json::to_string(&html)?;
+        tracker
+            .browser()
+            .evaluate(&format!("{RENDER}({argument})"))?;
+        self.shown = html;
+        self.last_render = Instant::now();
+        Ok(None)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn css(color: Color32) -> String {
+    format!("rgb({}, {}, {})", color.r(), color.g(), color.b())
+}
+
+/// The same table as the window, as html
+pub fn render(view: &View) -> String {
+    let mut html = String::from("<table><tr><th>Player</th>");
+    for (name, color) in RESOURCE_COLUMNS {
+        let _ = write!(html, r#"<th style="color: {}">{name}</th>"#, css(color));
+    }
+    html.push_str("<th>Total</th></tr>");
+
+    for row in &view.rows {
+        let background = if row.active {
+            format!("; background: {}", css(ACTIVE_PLAYER))
+        } else {
+            String::new()
+        };
+        let _ = write!(
+            html,
+            r#"<tr><td style="color: {}{background}">{}</td>"#,
+            css(row.color),
+            escape(&row.name)
+        );
+        for cell in &row.cells {
+            let _ = write!(
+                html,
+                r#"<td style="color: {}">{}</td>"#,
+                css(cell.color),
+                cell.text
+            );
+        }
+        let _ = write!(html, "<td>{:>5.2}</td></tr>", row.total);
+    }
+    html.push_str("<tr><td>Totals:</td>");
+    for total in &view.totals {
+        let _ = write!(html, "<td>{total:>5.2}</td>");
+    }
+    let _ = write!(html, "<td>{:>5}</td></tr></table>", view.states);
+    let _ = write!(html, "<div>{}</div>", escape(&view.turn));
+    if let Some(perspective) = &view.perspective {
+        let _ = write!(html, "<div>{}</div>", escape(perspective));
+    }
+
+    for warning in view.error.iter().chain(&view.warnings) {
+        let _ = write!(
+            html,
+            r#"<div class="colonizer-warning">{}</div>"#,
+            escape(warning)
+        );
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{log, USERNAME};
+    use crate::options::Options;
+    use cdp_mock::MockBrowser;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_show() {
+        let html = log("game1");
+        let mock = MockBrowser::start(&html);
+        let rendered = Arc::new(Mutex::new(Vec::new()));
+        let renders = Arc::clone(&rendered);
+        mock.on_evaluate(move |expression, _| {
+            if expression.starts_with(RENDER) {
+                renders.lock().unwrap().push(expression.to_owned());
+            }
+            json!("")
+        });
+
+        let options = Options {
+            username: USERNAME.to_owned(),
+            debugger: mock.debugger(),
+            ..Options::default()
+        };
+        let mut tracker = Tracker::new(&options);
+        tracker.state.update(&html);
+        let mut overlay = Overlay::default();
+        let view = View::new(&tracker.state, None);
+        overlay.present(&view, &mut tracker).unwrap();
+        // nothing changed
+        overlay.present(&view, &mut tracker).unwrap();
+
+        let rendered = rendered.lock().unwrap();
+        assert_eq!(rendered.len(), 1);
+        let argument = serde_json::to_string(&render(&view)).unwrap();
+        assert!(rendered[0].ends_with(&format!("({argument})")));
+        assert!(argument.contains("Magee#8353"));
+    }
+}
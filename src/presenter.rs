@@ -0,0 +1,223 @@
+//! What the frontends show, worked out once from the state, and the trait
+//! they show it through
+use crate::format::{best_odds, fmt_perspective, fmt_player, fmt_resource, fmt_turn, BEST_ODDS};
+use crate::state::{Record, State};
+use crate::tracker::Tracker;
+use cdp_client::Error;
+use colonizer_core::resource::N_RESOURCES;
+use egui::Color32;
+use std::time::Duration;
+
+/// A cell of the table, as `fmt_resource` formats it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub text: String,
+    /// `BEST_ODDS` when robbing the player is the best bet for the resource
+    pub color: Color32,
+}
+
+/// A player's row of the table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    /// The name to show, marked when a bot plays the seat
+    pub name: String,
+    pub color: Color32,
+    /// Whether it's the player's turn
+    pub active: bool,
+    pub cells: [Cell; N_RESOURCES],
+    /// The expected size of the player's hand
+    pub total: f64,
+}
+
+/// Everything a frontend shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct View {
+    pub rows: Vec<Row>,
+    /// The expected number of cards of each resource in all hands
+    pub totals: [f64; N_RESOURCES],
+    /// The number of possible states
+    pub states: usize,
+    /// e.g. `Turn 12: Dong rolled 8`
+    pub turn: String,
+    /// Whose view we're watching from, when spectating
+    pub perspective: Option<String>,
+    /// The seats the view can be switched to when spectating, by id and name
+    pub seats: Vec<(String, String)>,
+    /// The id of the seat whose view it is, if it's known
+    pub viewed: Option<String>,
+    /// Why the last poll failed
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+impl View {
+    /// The view of the current table
+    pub fn new(state: &State, error: Option<&Error>) -> Self {
+        Self::with_table(state, &state.build_table(), state.len(), error)
+    }
+
+    /// The view of a table of the game, e.g. one after an earlier message
+    pub fn with_table(
+        state: &State,
+        data: &[Record],
+        states: usize,
+        error: Option<&Error>,
+    ) -> Self {
+        let best = best_odds(data);
+        // running totals of each resource type
+        let mut totals = [0.0; N_RESOURCES];
+        let active = state.active_player();
+        let rows = data
+            .iter()
+            .map(|(name, color, cards)| {
+                let mut total = 0.0;
+                let cells = std::array::from_fn(|i| {
+                    let (sure, expected, rob_chance) = cards[i];
+                    total += expected; // row wise
+                    totals[i] += expected; // column wise
+                    Cell {
+                        text: fmt_resource(sure, expected, rob_chance),
+                        color: if rob_chance == best[i] {
+                            BEST_ODDS
+                        } else {
+                            Color32::WHITE
+                        },
+                    }
+                });
+                Row {
+                    name: fmt_player(state, name),
+                    color: *color,
+                    active: active == Some(name),
+                    cells,
+                    total,
+                }
+            })
+            .collect();
+        Self {
+            rows,
+            totals,
+            states,
+            turn: fmt_turn(state),
+            perspective: fmt_perspective(state),
+            seats: if state.spectating() {
+                state
+                    .seats()
+                    .iter()
+                    .map(|seat| (seat.id.clone(), seat.name.clone()))
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            viewed: state.perspective().map(str::to_owned),
+            error: error.map(|e| format!("{e}, retrying")),
+            warnings: state.warnings().to_vec(),
+        }
+    }
+}
+
+/// Something the user asked for through a frontend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    /// Watch from the view of the seat with this id, or `None` to work it out
+    /// from the log again
+    Perspective(Option<String>),
+}
+
+/// Carries out what the user asked for, the same way for every frontend
+pub fn handle(tracker: &mut Tracker, input: Input) {
+    match input {
+        Input::Perspective(perspective) => tracker.state.set_perspective(perspective),
+    }
+}
+
+/// A frontend, which shows the view after every poll
+pub trait Presenter {
+    /// Shows the view, and returns what the user asked for through it, if
+    /// anything. Fails when the presenter can't show it, e.g. when the page it
+    /// renders into is unreachable.
+    fn present(&mut self, view: &View, tracker: &mut Tracker) -> Result<Option<Input>, Error>;
+
+    /// Whether the user closed the presenter
+    fn closed(&mut self) -> bool {
+        false
+    }
+}
+
+/// Follows the game and presents it after every poll, until the presenter is
+/// closed. Its errors are printed, as it can't show them itself.
+pub fn run(mut tracker: Tracker, presenter: &mut impl Presenter) {
+    let mut last_error = None;
+    while !presenter.closed() {
+        let error = tracker.poll().err();
+        let view = View::new(&tracker.state, error.as_ref());
+        let message = match presenter.present(&view, &mut tracker) {
+            Ok(input) => {
+                if let Some(input) = input {
+                    handle(&mut tracker, input);
+                }
+                None
+            }
+            Err(e) => Some(e.to_string()),
+        };
+        if message != last_error {
+            if let Some(message) = &message {
+                eprintln!("{message}, retrying");
+            }
+            last_error = message;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    tracker.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{log, replay};
+    use crate::options::Options;
+
+    #[test]
+    fn test_view() {
+        let state = replay("game1");
+        let view = View::new(&state, None);
+
+        let data = state.build_table();
+        assert_eq!(view.rows.len(), data.len());
+        assert_eq!(view.states, state.len());
+        for (row, (name, _, cards)) in view.rows.iter().zip(&data) {
+            assert_eq!(row.name, *name);
+            let total: f64 = cards.iter().map(|&(_, expected, _)| expected).sum();
+            assert_eq!(row.total, total);
+        }
+        // the players with the best odds of robbing each resource stand out
+        let best = best_odds(&data);
+        for (row, (_, _, cards)) in view.rows.iter().zip(&data) {
+            for (i, cell) in row.cells.iter().enumerate() {
+                assert_eq!(cell.color == BEST_ODDS, cards[i].2 == best[i]);
+            }
+        }
+        assert_eq!(
+            View::new(&state, Some(&Error::Disconnected)).error,
+            Some(format!("{}, retrying", Error::Disconnected))
+        );
+    }
+
+    #[test]
+    fn test_switch_view() {
+        let mut tracker = Tracker::new(&Options {
+            spectate: true,
+            ..Options::default()
+        });
+        tracker.state.update(&log("game1"));
+        let view = View::new(&tracker.state, None);
+        assert_eq!(view.seats.len(), tracker.state.seats().len());
+
+        let (id, _) = view.seats[1].clone();
+        handle(&mut tracker, Input::Perspective(Some(id.clone())));
+        assert_eq!(tracker.state.perspective(), Some(id.as_str()));
+        assert_eq!(View::new(&tracker.state, None).viewed, Some(id));
+
+        // players can't switch the view
+        assert!(View::new(&replay("game1"), None).seats.is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+//! Records the log of a game so it can be replayed later, e.g. as a test
+//! fixture or attached to a bug report.
+//!
+//! A recording is a directory with:
+//! - `messages.jsonl`: the new log messages of every poll, one `Poll` per line
+//! - `log.html`: the full log, in the same format as `games/*.html`. When the
+//!   page's log starts over, e.g. for a new game, the earlier one is kept as
+//!   `log-1.html`, `log-2.html` and so on
+//! - `metadata.json`: the `Metadata` of the game
+//! - `page.html`: the latest snapshot of the game page, taken every 30 seconds,
+//!   from which fixtures for the player panels and our hand can be cut
+//!
+//! The full log and the metadata are written every few seconds and when the
+//! recording is saved, while the messages are written as they arrive.
+use crate::state::{align, State};
+use colonizer_core::message::{parse_messages, Message};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MESSAGES: &str = "messages.jsonl";
+const LOG: &str = "log.html";
+const METADATA: &str = "metadata.json";
+const PAGE: &str = "page.html";
+/// How often the full log and the metadata are written
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Splits a log into the outer html of its messages
+pub fn split_messages(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse(".message_post").unwrap();
+    document.select(&selector).map(|m| m.html()).collect()
+}
+
+/// The messages that appeared in the log since the previous poll
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Poll {
+    /// Milliseconds since the recording started
+    pub elapsed_ms: u64,
+    /// The outer html of each message
+    pub messages: Vec<String>,
+    /// Whether the messages are the page's whole log read again, as it didn't
+    /// line up with the messages recorded before, e.g. for a new game
+    #[serde(default)]
+    pub full: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Our username, which the log refers to as "you", or empty when spectating
+    pub username: String,
+    /// The color of each player's messages
+    pub colors: BTreeMap<String, [u8; 3]>,
+    /// Who won, if the game finished while recording
+    pub winner: Option<String>,
+    /// When the recording started, in seconds since the unix epoch
+    pub started: u64,
+}
+
+pub struct Recorder {
+    dir: PathBuf,
+    messages: BufWriter<File>,
+    /// Every message of the page's current log recorded so far
+    log: Vec<String>,
+    /// Fingerprints of `log`, to line the log up with when it's read again
+    seen: Vec<u64>,
+    /// How many logs were recorded before the current one
+    earlier_logs: usize,
+    start: Instant,
+    /// When the full log and the metadata were last written
+    saved: Instant,
+    metadata: Metadata,
+}
+
+/// Identifies a message like `State` does, as its html can come back with the
+/// attributes in another order
+fn fingerprint(message: &str) -> u64 {
+    parse_messages(message)
+        .first()
+        .map_or(0, Message::fingerprint)
+}
+
+impl Recorder {
+    /// Starts a recording in `dir`, creating it if needed
+    pub fn create(dir: &Path, username: &str) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let messages = BufWriter::new(File::create(dir.join(MESSAGES))?);
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Ok(Self {
+            dir: dir.to_owned(),
+            messages,
+            log: Vec::new(),
+            seen: Vec::new(),
+            earlier_logs: 0,
+            start: Instant::now(),
+            saved: Instant::now(),
+            metadata: Metadata {
+                username: username.to_owned(),
+                started,
+                ..Metadata::default()
+            },
+        })
+    }
+
+    /// Records the messages of the full log that weren't recorded yet. They're
+    /// found by lining the log up with the messages recorded before, like
+    /// `State::update` does, and the whole log is recorded if it doesn't line up.
+    pub fn record_log(&mut self, html: &str, state: &State) -> io::Result<()> {
+        let messages = split_messages(html);
+        let log: Vec<u64> = messages.iter().map(|m| fingerprint(m)).collect();
+        match align(&self.seen, &log) {
+            Some(start) => {
+                self.seen.extend_from_slice(&log[start..]);
+                self.write(messages[start..].to_vec(), false, state)
+            }
+            None => {
+                // keep the earlier log, which the page no longer shows
+                self.save()?;
+                self.earlier_logs += 1;
+                let earlier = self.dir.join(format!("log-{}.html", self.earlier_logs));
+                fs::rename(self.dir.join(LOG), earlier)?;
+                self.log.clear();
+                self.seen = log;
+                self.write(messages, true, state)
+            }
+        }
+    }
+
+    /// Records a message pushed by the log observer
+    pub fn record_message(&mut self, html: &str, state: &State) -> io::Result<()> {
+        let messages = split_messages(html);
+        self.seen.extend(messages.iter().map(|m| fingerprint(m)));
+        self.write(messages, false, state)
+    }
+
+    /// Appends the messages of a poll, and saves the full log and the metadata
+    /// every few seconds or once the game is won
+    fn write(&mut self, messages: Vec<String>, full: bool, state: &State) -> io::Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let poll = Poll {
+            elapsed_ms: self.start.elapsed().as_millis() as u64,
+            messages,
+            full,
+        };
+        serde_json::to_writer(&mut self.messages, &poll)?;
+        writeln!(self.messages)?;
+        self.messages.flush()?;
+        self.log.extend(poll.messages);
+
+        self.metadata.colors = state
+            .seats()
+            .iter()
+            .filter_map(|seat| {
+                let color = seat.color?;
+                Some((seat.name.clone(), [color.r(), color.g(), color.b()]))
+            })
+            .collect();
+        let winner = state.winner().map(str::to_owned);
+        let won = winner.is_some() && self.metadata.winner != winner;
+        self.metadata.winner = winner;
+        if won || self.saved.elapsed() >= SAVE_INTERVAL {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the snapshot of the game page
+    pub fn record_page(&mut self, html: &str) -> io::Result<()> {
+        fs::write(self.dir.join(PAGE), html)
+    }
+
+    /// Writes the full log and the metadata, so the recording is complete
+    pub fn save(&mut self) -> io::Result<()> {
+        self.saved = Instant::now();
+        fs::write(self.dir.join(LOG), self.log.join("\n"))?;
+        let metadata = serde_json::to_string_pretty(&self.metadata)?;
+        fs::write(self.dir.join(METADATA), metadata)
+    }
+}
+
+/// A recording read back from its directory
+pub struct Recording {
+    pub metadata: Metadata,
+    pub polls: Vec<Poll>,
+}
+
+impl Recording {
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        let metadata = serde_json::from_str(&fs::read_to_string(dir.join(METADATA))?)?;
+        let mut polls = Vec::new();
+        for line in BufReader::new(File::open(dir.join(MESSAGES))?).lines() {
+            polls.push(serde_json::from_str(&line?)?);
+        }
+        Ok(Self { metadata, polls })
+    }
+
+    /// Feeds the messages to a new state in the order they were recorded
+    pub fn replay(&self) -> State {
+        let mut state = if self.metadata.username.is_empty() {
+            State::spectator()
+        } else {
+            State::new(self.metadata.username.clone())
+        };
+        for poll in &self.polls {
+            if poll.full {
+                state.update(&poll.messages.join("\n"));
+                continue;
+            }
+            for message in &poll.messages {
+                state.update_message(message);
+            }
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{assert_same_table, log, USERNAME};
+
+    #[test]
+    fn test_record() {
+        let html = log("game1");
+        let dir = std::env::temp_dir().join(format!("colonizer-record-{}", std::process::id()));
+        let mut recorder = Recorder::create(&dir, USERNAME).unwrap();
+        let mut state = State::new(USERNAME.to_owned());
+
+        // the log when the observer is installed, then the messages it pushes
+        let messages = split_messages(&html);
+        let (initial, pushed) = messages.split_at(40);
+        let initial = initial.join("\n");
+        state.update(&initial);
+        recorder.record_log(&initial, &state).unwrap();
+        for message in pushed {
+            state.update_message(message);
+            recorder.record_message(message, &state).unwrap();
+        }
+        // the page reloaded and the observer was installed again, with the
+        // start of the log trimmed
+        let trimmed = messages[10..].join("\n");
+        state.update(&trimmed);
+        recorder.record_log(&trimmed, &state).unwrap();
+
+        let recording = Recording::load(&dir).unwrap();
+        assert_eq!(recording.polls.len(), 1 + pushed.len());
+        assert_eq!(recording.metadata.winner.as_deref(), Some(USERNAME));
+        assert_eq!(recording.metadata.colors.len(), 4);
+        assert_same_table(&recording.replay(), &state);
+
+        let mut full = State::new(USERNAME.to_owned());
+        full.update(&fs::read_to_string(dir.join(LOG)).unwrap());
+        assert_same_table(&full, &state);
+
+        recorder.record_page("<html></html>").unwrap();
+        assert_eq!(fs::read_to_string(dir.join(PAGE)).unwrap(), "<html></html>");
+
+        // a new game starts a new log, which is recorded whole
+        let next = log("game3");
+        state.update(&next);
+        recorder.record_log(&next, &state).unwrap();
+        recorder.save().unwrap();
+        let recording = Recording::load(&dir).unwrap();
+        assert!(recording.polls.last().unwrap().full);
+        assert_same_table(&recording.replay(), &state);
+        let saved = |name: &str| split_messages(&fs::read_to_string(dir.join(name)).unwrap());
+        assert_eq!(saved("log-1.html").len(), messages.len());
+        assert_eq!(saved(LOG).len(), split_messages(&next).len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
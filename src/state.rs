@@ -1,74 +1,141 @@
 //! Parses game lock
 //! Updates different trackers
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::card_tracker::MAX_PLAYERS;
-use crate::html_parser;
-use crate::resource::{Resource, N_RESOURCES};
-use crate::{card_tracker::CardTracker, hand::Hand};
+use colonizer_core::card_tracker::MAX_PLAYERS;
+use colonizer_core::event::{Event, Item};
+use colonizer_core::html_parser::{OwnHand, PlayerCounts};
+use colonizer_core::message::{parse_messages, Chat, Kind, Message, Token};
+use colonizer_core::resource::N_RESOURCES;
+use colonizer_core::{card_tracker::CardTracker, hand::Hand};
 use egui::Color32;
-use lazy_static::lazy_static;
-use regex::Regex;
 
-const NAME: &str = r"(\w+(?:#\d+)?)";
-const CARDS: &str = r"((?:(?:lumber|brick|wool|grain|ore|card) ?)+)";
-const ITEM_PTTN: &str = r"(road|settlement|city|development card)";
-
-type Callback = fn(&mut State, &[&str]) -> ();
 pub type Record = (String, Color32, [(u8, f64, f64); N_RESOURCES]);
 
-lazy_static! {
-    static ref PATTERNS: [(Regex, Callback); 9] = [
-        (
-            Regex::new(&format!(
-                r"{NAME} (?:got|received starting resources) {CARDS}"
-            ))
-            .unwrap(),
-            State::handle_receive,
-        ),
-        (
-            Regex::new(&format!(r"{NAME} discarded {CARDS}")).unwrap(),
-            State::handle_discard,
-        ),
-        (
-            Regex::new(&format!(r"{NAME} (?:built a|bought) {ITEM_PTTN}")).unwrap(),
-            State::handle_purchase,
-        ),
-        (
-            Regex::new(&format!(r"{NAME} stole {CARDS} from {NAME}")).unwrap(),
-            State::handle_rob,
-        ),
-        (
-            Regex::new(&format!(r"{NAME} wants to give {CARDS} for {CARDS}")).unwrap(),
-            State::handle_trade_offer,
-        ),
-        (
-            Regex::new(&format!(r"{NAME} traded {CARDS} for {CARDS} with {NAME}")).unwrap(),
-            State::handle_trade,
-        ),
-        (
-            Regex::new(&format!(r"{NAME} took from bank {CARDS}")).unwrap(),
-            State::handle_year_of_plenty,
-        ),
-        (
-            Regex::new(&format!(r"{NAME} gave bank {CARDS} and took {CARDS}")).unwrap(),
-            State::handle_bank_trade,
-        ),
-        (
-            Regex::new(&format!(r"{NAME} stole (\d+) {CARDS}")).unwrap(),
-            State::handle_monopoly,
-        ),
-    ];
+/// The events from a roll up to the next one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Turn {
+    /// Who rolled, `None` for the setup
+    pub player: Option<String>,
+    pub events: Vec<Event>,
+}
+
+/// A seat at the table. It keeps its color and avatar when the player in it
+/// goes by another name, e.g. when a bot takes over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Seat {
+    /// The first name seen in the seat, which the events refer to it by
+    pub id: String,
+    /// The name the seat goes by now
+    pub name: String,
+    /// The color of the seat's messages
+    pub color: Option<Color32>,
+    /// The avatar in front of the name, e.g. `Guest`, `User` or `bot`
+    pub icon: Option<String>,
+    /// Whether a bot plays the seat since its player left
+    pub bot: bool,
+}
+
+/// A game that ended or was left, kept when the next one starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedGame {
+    pub winner: Option<String>,
+    /// The table as it was at the end
+    pub table: Vec<Record>,
+    /// The number of turns played
+    pub turns: usize,
+}
+
+/// A message of the log, with what it was understood as
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    /// The message as plain text
+    pub text: String,
+    pub kind: Kind,
+    /// The event the message describes, as the log names the players
+    pub event: Option<Event>,
+    /// The turn the message is in, 0 during the setup
+    pub turn: usize,
+    /// The number of possible states after the message
+    pub states: usize,
+    /// The table after the event, which other messages leave as it was
+    pub table: Option<Vec<Record>>,
+}
+
+impl LogLine {
+    /// Whether the game posted the message about a player, but no pattern matched it
+    pub fn unparsed(&self) -> bool {
+        self.kind == Kind::Event && self.event.is_none()
+    }
+}
+
+/// How many of the last messages read have to be found together in a log to
+/// line it up with them
+const ANCHOR: usize = 8;
+/// How many cards of each resource the game has
+const BANK_CARDS: u8 = 19;
+
+/// Finds where the messages read before end in `log`, so the messages after are
+/// new. The log can have lost messages at its start or gained some in between.
+/// `None` if the last messages read aren't in it.
+pub fn align(seen: &[u64], log: &[u64]) -> Option<usize> {
+    if log.starts_with(seen) {
+        return Some(seen.len());
+    }
+    let anchor = &seen[seen.len().saturating_sub(ANCHOR)..];
+    // the same messages can come up more than once, so take the ones nearest
+    // to where they were
+    log.windows(anchor.len())
+        .enumerate()
+        .filter(|(_, window)| *window == anchor)
+        .map(|(i, _)| i + anchor.len())
+        .min_by_key(|&end| end.abs_diff(seen.len()))
 }
 
 pub struct State {
-    /// The player's username
-    username: String,
-    /// Maps player names to their index
+    /// Who the log refers to as "you", i.e. whose point of view the page shows.
+    /// When spectating it's inferred from the log, and `None` until then.
+    perspective: Option<String>,
+    /// Whether we're watching rather than playing, so the perspective can change
+    spectating: bool,
+    /// Indexed like the card tracker
+    seats: Vec<Seat>,
+    /// Maps every name a seat has gone by to its index
     players: HashMap<String, usize>,
-    colors: HashMap<String, Color32>,
-    last_line: usize,
+    /// The players seen in the setup and the player panels, whose names are
+    /// looked for in the log
+    names: HashSet<String>,
+    /// Player names in turn order, from the setup placements
+    turn_order: Vec<String>,
+    /// The setup followed by every turn so far
+    turns: Vec<Turn>,
+    /// Whose turn it is, or who's placing during the setup
+    active_player: Option<String>,
+    /// Fingerprints of the log's messages up to the last one processed, by
+    /// their position in the log
+    seen: Vec<u64>,
     card_tracker: CardTracker,
+    /// Number of unplayed development cards each player holds
+    dev_cards: [u8; MAX_PLAYERS],
+    /// Mismatches between the tracked hands and the counts shown on the page
+    warnings: Vec<String>,
+    /// The last warning about each seat's counts, which the next one replaces
+    seat_warnings: HashMap<usize, String>,
+    /// The resource and development card counts each seat's panel showed at
+    /// the last poll
+    shown_counts: HashMap<usize, (u8, u8)>,
+    /// Our hand as the page showed it at the last poll
+    shown_hand: Option<OwnHand>,
+    /// Who won, once the game is over
+    winner: Option<String>,
+    /// What the players typed in the log, which is kept apart from the events
+    chat: Vec<Chat>,
+    /// Every message of the game so far
+    log: Vec<LogLine>,
+    /// The earlier games in the same tab, oldest first
+    archive: Vec<ArchivedGame>,
+    /// How many times tracking started over, e.g. for a new game
+    generation: usize,
     // dice_tracker: DiceTracker,
     // devcard_tracker: DevCardTracker,
 }
@@ -76,11 +143,35 @@ pub struct State {
 impl State {
     pub fn new(username: String) -> Self {
         Self {
-            username,
+            perspective: Some(username),
+            spectating: false,
+            seats: Vec::new(),
             players: HashMap::new(),
-            colors: Default::default(),
-            last_line: 0,
+            names: HashSet::new(),
+            turn_order: Vec::new(),
+            turns: vec![Turn::default()],
+            active_player: None,
+            seen: Vec::new(),
             card_tracker: CardTracker::default(),
+            dev_cards: [0; MAX_PLAYERS],
+            warnings: Vec::new(),
+            seat_warnings: HashMap::new(),
+            shown_counts: HashMap::new(),
+            shown_hand: None,
+            winner: None,
+            chat: Vec::new(),
+            log: Vec::new(),
+            archive: Vec::new(),
+            generation: 0,
+        }
+    }
+
+    /// Watches a game we aren't playing in
+    pub fn spectator() -> Self {
+        Self {
+            perspective: None,
+            spectating: true,
+            ..Self::new(String::new())
         }
     }
 
@@ -88,167 +179,1214 @@ impl State {
         self.card_tracker.len()
     }
 
-    fn normalize(&self, s: &str) -> String {
-        // remove consecutive spaces and newlines
-        let s = s
-            .trim()
-            .replace(':', "")
-            .replace("you", &self.username)
-            .replace("You", &self.username)
-            .split([' ', '\n'])
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<_>>()
-            .join(" ");
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn seats(&self) -> &[Seat] {
+        &self.seats
+    }
+
+    /// Every name the seats have gone by, with the index of the seat
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.players
+            .iter()
+            .map(|(name, &seat)| (name.as_str(), seat))
+    }
+
+    /// The seat that goes or went by `name`
+    pub fn seat(&self, name: &str) -> Option<&Seat> {
+        self.players.get(name).map(|&i| &self.seats[i])
+    }
+
+    /// The color of the seat that goes or went by `name`
+    pub fn color(&self, name: &str) -> Option<Color32> {
+        self.seat(name).and_then(|seat| seat.color)
+    }
+
+    /// The name the seat that goes or went by `name` goes by now
+    pub fn display_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.players
+            .get(name)
+            .map_or(name, |&i| &self.seats[i].name)
+    }
+
+    pub fn winner(&self) -> Option<&str> {
+        self.winner.as_deref()
+    }
+
+    /// Whose point of view the page shows, if we know
+    pub fn perspective(&self) -> Option<&str> {
+        self.perspective.as_deref()
+    }
+
+    /// Switches to another player's view, e.g. when the page changes it
+    pub fn set_perspective(&mut self, perspective: Option<String>) {
+        self.perspective = perspective.map(|name| self.seat_id(&name));
+    }
+
+    pub fn spectating(&self) -> bool {
+        self.spectating
+    }
+
+    pub fn chat(&self) -> &[Chat] {
+        &self.chat
+    }
+
+    pub fn log(&self) -> &[LogLine] {
+        &self.log
+    }
 
-        // fix for usernames prefixed with resources
-        let re = Regex::new("(from|with|User|Guest) (brick|lumber|wool|grain|ore) ").unwrap();
-        let s = re.replace_all(&s, "$1 $2");
-        s.into_owned()
+    /// The table after the `i`th message of the log, as the last event up to
+    /// it left it
+    pub fn table_at(&self, i: usize) -> &[Record] {
+        self.log[..=i]
+            .iter()
+            .rev()
+            .find_map(|line| line.table.as_deref())
+            .unwrap_or_default()
     }
 
+    pub fn archive(&self) -> &[ArchivedGame] {
+        &self.archive
+    }
+
+    /// Changes whenever tracking starts over, which starts a new log
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// The number of the current turn, 0 during the setup
+    pub fn current_turn(&self) -> usize {
+        self.turns.len() - 1
+    }
+
+    pub fn active_player(&self) -> Option<&str> {
+        self.active_player
+            .as_deref()
+            .map(|name| self.display_name(name))
+    }
+
+    /// Processes the messages of the full game log that haven't been seen yet.
+    /// They're found by lining the log up with the messages read before, as
+    /// the page can drop old messages or insert some.
     pub fn update(&mut self, html: &str) {
-        let lines = html_parser::parse(html);
-        let tmp = lines.len();
-        for ((r, g, b), line) in lines.into_iter().skip(self.last_line) {
-            let line = self.normalize(&line);
-            if line.contains("starting") {
-                let name = line.split(' ').nth(1).unwrap();
-                self.colors
-                    .insert(name.to_owned(), Color32::from_rgb(r, g, b));
+        let messages = parse_messages(html);
+        let log: Vec<u64> = messages.iter().map(Message::fingerprint).collect();
+        let start = match align(&self.seen, &log) {
+            Some(start) => {
+                // the log may have lost its start, but anything else before
+                // `start` wasn't there when it was read
+                if !self.seen.ends_with(&log[..start]) {
+                    self.warnings.push(
+                        "Messages were added to the log among the ones already read, and were skipped"
+                            .to_owned(),
+                    );
+                }
+                start
+            }
+            // a shorter log, or one after the game ended, is the next game's
+            None if log.len() < self.seen.len() || self.winner.is_some() => {
+                self.reset();
+                0
+            }
+            None => {
+                let earlier = self.start_over();
+                self.names = earlier.names;
+                self.warnings = earlier.warnings;
+                self.warnings.push(
+                    "The log doesn't match the messages read before, so it was read again from the start"
+                        .to_owned(),
+                );
+                0
+            }
+        };
+        self.seen = log[..start].to_vec();
+        for (message, fingerprint) in messages.into_iter().zip(log).skip(start) {
+            self.handle_message(message);
+            self.seen.push(fingerprint);
+        }
+    }
+
+    /// Processes a single new `.message_post` node appended to the log
+    pub fn update_message(&mut self, html: &str) {
+        for message in parse_messages(html) {
+            let fingerprint = message.fingerprint();
+            self.handle_message(message);
+            self.seen.push(fingerprint);
+        }
+    }
+
+    /// Starts tracking from scratch and returns the state so far. The messages
+    /// read and the earlier games carry over, as they're about the tab.
+    fn start_over(&mut self) -> Self {
+        let fresh = Self {
+            perspective: self.perspective.clone(),
+            spectating: self.spectating,
+            seen: std::mem::take(&mut self.seen),
+            archive: std::mem::take(&mut self.archive),
+            generation: self.generation + 1,
+            ..Self::new(String::new())
+        };
+        std::mem::replace(self, fresh)
+    }
+
+    /// Archives the current game and starts over, from the same point of view
+    /// unless we're spectating
+    fn reset(&mut self) {
+        let finished = self.start_over();
+        if self.spectating {
+            self.perspective = None;
+        }
+        self.archive.push(ArchivedGame {
+            winner: finished.winner.clone(),
+            table: finished.build_table(),
+            turns: finished.current_turn(),
+        });
+    }
+
+    fn handle_message(&mut self, mut message: Message) {
+        // the log of the next game starts with the welcome notices
+        if message.is_welcome() && !self.seats.is_empty() {
+            self.reset();
+        }
+        if !self.names.is_empty() {
+            message.resolve(&self.names);
+        }
+        let event = self.process(&message);
+        let table = event.is_some().then(|| self.build_table());
+        self.log.push(LogLine {
+            text: message.text(),
+            kind: message.kind,
+            event,
+            turn: self.current_turn(),
+            states: self.len(),
+            table,
+        });
+    }
+
+    /// Updates the state with a message, returning the event it describes
+    fn process(&mut self, message: &Message) -> Option<Event> {
+        match &message.kind {
+            Kind::Event => {}
+            Kind::Chat(chat) => {
+                self.chat.push(chat.clone());
+                return None;
+            }
+            Kind::Notice => {
+                // the game announces who left or rejoined without their seat's color
+                if let Some(event @ (Event::Left { .. } | Event::Rejoined { .. })) =
+                    message.event(self.perspective.as_deref())
+                {
+                    if let Event::Rejoined { player } = &event {
+                        // back under their own name
+                        if let Some(&seat) = self.players.get(player) {
+                            self.seats[seat].name.clone_from(player);
+                        }
+                    }
+                    self.apply_logged(event.clone());
+                    return Some(event);
+                }
+                return None;
             }
-            self.handle_line(&line);
         }
-        self.last_line = tmp;
+        let (r, g, b) = message.color;
+        let color = Color32::from_rgb(r, g, b);
+        match message.subject() {
+            Some((Token::Player { name, icon }, _)) => self.seat_player(name, icon, color),
+            // the page's player's messages are in their seat's color too
+            Some((Token::You, _)) if self.spectating => {
+                if let Some(seat) = self.seats.iter().find(|seat| seat.color == Some(color)) {
+                    self.perspective = Some(seat.id.clone());
+                }
+            }
+            _ => {}
+        }
+        if let Some(winner) = message.winner() {
+            self.winner = Some(winner.to_owned());
+        }
+        if self.spectating {
+            self.infer_perspective(message);
+        }
+        let event = message.event(self.perspective.as_deref());
+        match &event {
+            Some(event) => self.apply_logged(event.clone()),
+            None if self.perspective.is_none() && message.tokens.contains(&Token::You) => {
+                self.warnings.push(
+                    "Skipped a message about the player whose view this is, who isn't known yet"
+                        .to_owned(),
+                );
+            }
+            None => {}
+        }
+        event
     }
 
-    /// Returns the index for a given player
-    /// If the player is not in the tracker, it will be added
-    ///
-    /// # Panics
-    ///
-    /// Panics if the number of players is greater than `MAX_PLAYERS`
-    fn get_player_index(&mut self, name: &str) -> usize {
-        let i = self.players.len();
-        let tmp = *self.players.entry(name.to_owned()).or_insert(i);
-
-        assert!(
-            self.players.len() <= MAX_PLAYERS,
-            "Too many players! {:?}",
-            self.players.keys().collect::<Vec<_>>()
-        );
-        tmp
-    }
-
-    pub fn handle_line(&mut self, line: &str) {
-        for (regex, event) in PATTERNS.iter() {
-            if let Some(caps) = regex.captures(line) {
-                let line = caps
-                    .iter()
-                    // skip over capture group 0
-                    .skip(1)
-                    .map(|m| m.unwrap().as_str())
-                    .collect::<Vec<_>>();
-                event(self, &line);
-                return;
-            }
-        }
-    }
-
-    fn handle_receive(&mut self, line: &[&str]) {
-        println!("{} got {}", line[0], line[1]);
-        let player = self.get_player_index(line[0]);
-        self.card_tracker.add(player, Hand::from(line[1]));
-    }
-
-    fn handle_discard(&mut self, line: &[&str]) {
-        println!("{} discarded {}", line[0], line[1]);
-        let player = self.get_player_index(line[0]);
-        self.card_tracker.remove(player, Hand::from(line[1]));
-    }
-
-    fn handle_purchase(&mut self, line: &[&str]) {
-        println!("{} purchased {}", line[0], line[1]);
-        let player = self.get_player_index(line[0]);
-        let cost = match line[1] {
-            "road" => Hand::from([1, 1, 0, 0, 0]),
-            "settlement" => Hand::from([1, 1, 1, 1, 0]),
-            "city" => Hand::from([0, 0, 0, 2, 3]),
-            "development card" => Hand::from([0, 0, 1, 1, 1]),
-            _ => panic!("Unknown item: {}", line[1]),
+    /// Applies an event from the log, which can refer to a seat by any of its names
+    fn apply_logged(&mut self, event: Event) {
+        // players are seated by the messages they post, in their seat's color
+        let event = event.try_rename_players(|name| match self.players.get(name) {
+            Some(&seat) => Ok(self.seats[seat].id.clone()),
+            None => Err(name.to_owned()),
+        });
+        match event {
+            Ok(event) => self.apply(event),
+            Err(name) => self.warnings.push(format!(
+                "{name} isn't at the table, so a message about them was skipped"
+            )),
+        }
+    }
+
+    /// Works out whose point of view the page shows from the robs involving
+    /// "you", and follows it when it changes
+    fn infer_perspective(&mut self, message: &Message) {
+        let Some((subject, verb)) = message.subject() else {
+            return;
+        };
+        if !verb.starts_with("stole") {
+            return;
+        }
+        match (subject, message.tokens.last()) {
+            // only the player whose turn it is can rob
+            (Token::You, _) if self.perspective.is_none() => {
+                self.perspective = self.active_player.clone();
+            }
+            // nobody robs themselves, so the view has changed to someone we can't tell
+            (Token::Player { name, .. }, Some(Token::You))
+                if self.perspective.as_ref() == Some(name) =>
+            {
+                self.perspective = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Finds the seat of the player a message is about by its color, so a
+    /// new name in a known seat becomes another name of that seat
+    fn seat_player(&mut self, name: &str, icon: &Option<String>, color: Color32) {
+        let seat = match self.players.get(name) {
+            Some(&seat) => seat,
+            None => match self.seats.iter().position(|seat| seat.color == Some(color)) {
+                Some(seat) => {
+                    self.players.insert(name.to_owned(), seat);
+                    self.seats[seat].name = name.to_owned();
+                    seat
+                }
+                None => match self.get_player_index(name) {
+                    Some(seat) => seat,
+                    None => return,
+                },
+            },
         };
-        self.card_tracker.remove(player, cost);
+        let seat = &mut self.seats[seat];
+        seat.color.get_or_insert(color);
+        seat.name = name.to_owned();
+        if let Some(icon) = icon {
+            // a bot's avatar in a seat a person played means they left, and
+            // the other way around that they're back
+            match (seat.icon.as_deref(), icon.as_str()) {
+                (Some(old), "bot") if old != "bot" => seat.bot = true,
+                (Some("bot"), new) if new != "bot" => seat.bot = false,
+                _ => {}
+            }
+            seat.icon = Some(icon.clone());
+        }
     }
 
-    fn handle_rob(&mut self, line: &[&str]) {
-        println!("{} stole {} from {}", line[0], line[1], line[2]);
-        let robber = self.get_player_index(line[0]);
-        let victim = self.get_player_index(line[2]);
+    /// The name the events use for the seat that goes or went by `name`
+    fn seat_id(&self, name: &str) -> String {
+        self.players
+            .get(name)
+            .map_or(name, |&i| &self.seats[i].id)
+            .to_owned()
+    }
 
-        if line[1] == "card" {
-            // we don't know which card was stolen
-            self.card_tracker.rob(robber, victim);
-        } else {
-            // rob involving ourselves, so we know which card was stolen
-            self.card_tracker.add(robber, Hand::from(line[1]));
-            self.card_tracker.remove(victim, Hand::from(line[1]));
+    /// Adds players whose names should be looked for in the log, e.g. from the
+    /// player panels
+    pub fn add_names<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        self.names.extend(names.into_iter().map(str::to_owned));
+    }
+
+    /// Cross-checks the tracked hands against the card counts shown in the
+    /// player panels, once a panel has shown the same counts for two polls,
+    /// as the panels can be ahead of the log. On a mismatch the hand is
+    /// re-anchored to the page.
+    pub fn update_counts(&mut self, counts: &[PlayerCounts]) {
+        for count in counts {
+            let name = &count.name;
+            let Some(&player) = self.players.get(name) else {
+                continue;
+            };
+            let shown = (count.resources, count.dev_cards);
+            if self.shown_counts.insert(player, shown) == Some(shown) {
+                self.check_counts(player, count);
+            }
         }
     }
 
-    fn handle_trade_offer(&mut self, line: &[&str]) {
-        println!("{} offered {} for {}", line[0], line[1], line[2]);
-        let player = self.get_player_index(line[0]);
-        self.card_tracker.know_has(player, Hand::from(line[1]));
+    /// Re-anchors a seat's hand to exact card counts that don't match it
+    fn check_counts(&mut self, player: usize, count: &PlayerCounts) {
+        let name = &count.name;
+        if !self.card_tracker.know_size(player, count.resources) {
+            self.warn_seat(
+                player,
+                format!(
+                    "{name} has {} resource cards, which the log can't explain",
+                    count.resources
+                ),
+            );
+            self.card_tracker.resize(player, count.resources);
+        }
+        if self.dev_cards[player] != count.dev_cards {
+            self.warn_seat(
+                player,
+                format!(
+                    "{name} has {} development cards, expected {}",
+                    count.dev_cards, self.dev_cards[player]
+                ),
+            );
+            self.dev_cards[player] = count.dev_cards;
+        }
     }
 
-    fn handle_trade(&mut self, line: &[&str]) {
-        println!(
-            "{} traded {} for {} with {}",
-            line[0], line[1], line[2], line[3]
-        );
-        let player = self.get_player_index(line[0]);
-        let counterparty = self.get_player_index(line[3]);
-        let offer = Hand::from(line[1]);
-        let request = Hand::from(line[2]);
-        self.card_tracker.add(player, request);
-        self.card_tracker.remove(counterparty, request);
-        self.card_tracker.add(counterparty, offer);
-        self.card_tracker.remove(player, offer);
+    /// Warns about a seat's hand, replacing the last warning about it so that
+    /// every poll doesn't add another
+    fn warn_seat(&mut self, seat: usize, warning: String) {
+        if let Some(old) = self.seat_warnings.insert(seat, warning.clone()) {
+            if let Some(i) = self.warnings.iter().position(|w| *w == old) {
+                self.warnings.remove(i);
+            }
+        }
+        self.warnings.push(warning);
     }
 
-    fn handle_year_of_plenty(&mut self, line: &[&str]) {
-        println!("{} took from bank {}", line[0], line[1]);
-        let player = self.get_player_index(line[0]);
-        self.card_tracker.add(player, Hand::from(line[1]));
+    /// Pins our own row to the hand shown on the page, once it has shown the
+    /// same hand for two polls. A hand that's empty, holds more of a resource
+    /// than the game has or doesn't match our panel is ignored, as it was
+    /// likely misread.
+    pub fn update_own_hand(&mut self, hand: OwnHand) {
+        if self.shown_hand.replace(hand) != Some(hand) {
+            return;
+        }
+        let Some(&player) = self
+            .perspective
+            .as_ref()
+            .and_then(|name| self.players.get(name))
+        else {
+            return;
+        };
+        let size = hand.resources.size();
+        let panel = self
+            .shown_counts
+            .get(&player)
+            .map(|&(resources, _)| resources);
+        if size == 0
+            || hand.resources.values().any(|count| count > BANK_CARDS)
+            || panel.is_some_and(|resources| resources != size)
+        {
+            return;
+        }
+        if !self.card_tracker.pin(player, hand.resources) {
+            self.warn_seat(
+                player,
+                format!(
+                    "Our hand on the page doesn't match the log, re-anchored to {}",
+                    hand.resources
+                ),
+            );
+        }
+        self.dev_cards[player] = hand.dev_cards;
     }
 
-    fn handle_bank_trade(&mut self, line: &[&str]) {
-        println!("{} gave bank {} for {}", line[0], line[1], line[2]);
-        let player = self.get_player_index(line[0]);
-        self.card_tracker.remove(player, Hand::from(line[1]));
-        self.card_tracker.add(player, Hand::from(line[2]));
+    /// Returns the index for a given player
+    /// If the player is not in the tracker, it will be added, unless every
+    /// seat is taken
+    fn get_player_index(&mut self, name: &str) -> Option<usize> {
+        if let Some(&i) = self.players.get(name) {
+            return Some(i);
+        }
+        if self.seats.len() == MAX_PLAYERS {
+            return None;
+        }
+        self.seats.push(Seat {
+            id: name.to_owned(),
+            name: name.to_owned(),
+            color: None,
+            icon: None,
+            bot: false,
+        });
+        let i = self.seats.len() - 1;
+        self.players.insert(name.to_owned(), i);
+        Some(i)
     }
 
-    fn handle_monopoly(&mut self, line: &[&str]) {
-        println!("{} monopolied {} {}", line[0], line[1], line[2]);
-        let player = self.get_player_index(line[0]);
-        self.card_tracker.monopoly(
-            player,
-            Resource::try_from(line[2]).unwrap(),
-            line[1].parse().unwrap(),
-        );
+    /// Updates the trackers with an event, whose players are all seated
+    fn apply(&mut self, event: Event) {
+        match &event {
+            Event::Roll { player, .. } => {
+                self.turns.push(Turn {
+                    player: Some(player.clone()),
+                    events: Vec::new(),
+                });
+                self.active_player = Some(player.clone());
+            }
+            // development cards can be played before rolling
+            Event::DevCardUsed { player } => self.active_player = Some(player.clone()),
+            Event::Placement { player, item } => {
+                // the settlements are placed in snake order, so the first
+                // placement of each player gives the seating
+                if *item == Item::Settlement && !self.turn_order.contains(player) {
+                    self.turn_order.push(player.clone());
+                    self.names.insert(player.clone());
+                }
+                self.active_player = Some(player.clone());
+            }
+            _ => {}
+        }
+        self.turns.last_mut().unwrap().events.push(event.clone());
+
+        // the seat whose cards the log missed, if any
+        let missed = match event {
+            Event::Receive { player, cards } | Event::YearOfPlenty { player, cards } => {
+                let player = self.players[&player];
+                self.card_tracker.add(player, cards);
+                None
+            }
+            Event::Discard { player, cards } => {
+                let player = self.players[&player];
+                (!self.card_tracker.remove(player, cards)).then_some(player)
+            }
+            Event::Purchase { player, item } => {
+                let player = self.players[&player];
+                if item == Item::DevelopmentCard {
+                    self.dev_cards[player] += 1;
+                }
+                (!self.card_tracker.remove(player, item.cost())).then_some(player)
+            }
+            Event::Placement { .. } | Event::Roll { .. } => None,
+            // the seat keeps its cards, whoever plays it
+            Event::Left { player } => {
+                let seat = self.players[&player];
+                self.seats[seat].bot = true;
+                None
+            }
+            Event::Rejoined { player } => {
+                let seat = self.players[&player];
+                self.seats[seat].bot = false;
+                None
+            }
+            Event::DevCardUsed { player } => {
+                let player = self.players[&player];
+                self.dev_cards[player] = self.dev_cards[player].saturating_sub(1);
+                None
+            }
+            Event::Rob {
+                robber,
+                victim,
+                card,
+            } => {
+                let robber = self.players[&robber];
+                let victim = self.players[&victim];
+                let consistent = if let Some(card) = card {
+                    // rob involving ourselves, so we know which card was stolen
+                    self.card_tracker.add(robber, Hand::from(card));
+                    self.card_tracker.remove(victim, Hand::from(card))
+                } else {
+                    // we don't know which card was stolen
+                    self.card_tracker.rob(robber, victim)
+                };
+                (!consistent).then_some(victim)
+            }
+            Event::RobFromUnknown { robber, card } => {
+                let robber = self.players[&robber];
+                let victims: Vec<usize> = (0..self.seats.len())
+                    .filter(|&seat| seat != robber)
+                    .collect();
+                if !self.card_tracker.rob_any(robber, card, &victims) {
+                    self.warnings.push(format!(
+                        "Nobody {} could have robbed held {}, so the rob was skipped",
+                        self.seats[robber].name,
+                        card.name()
+                    ));
+                }
+                None
+            }
+            Event::TradeOffer { player, offer } => {
+                let player = self.players[&player];
+                (!self.card_tracker.know_has(player, offer)).then_some(player)
+            }
+            Event::Trade {
+                player,
+                counterparty,
+                offer,
+                request,
+            } => {
+                let player = self.players[&player];
+                let counterparty = self.players[&counterparty];
+                self.card_tracker.add(player, request);
+                let given = self.card_tracker.remove(counterparty, request);
+                self.card_tracker.add(counterparty, offer);
+                if !self.card_tracker.remove(player, offer) {
+                    Some(player)
+                } else {
+                    (!given).then_some(counterparty)
+                }
+            }
+            Event::BankTrade {
+                player,
+                given,
+                taken,
+            } => {
+                let player = self.players[&player];
+                let consistent = self.card_tracker.remove(player, given);
+                self.card_tracker.add(player, taken);
+                (!consistent).then_some(player)
+            }
+            Event::Monopoly {
+                player,
+                resource,
+                count,
+            } => {
+                let player = self.players[&player];
+                (!self.card_tracker.monopoly(player, resource, count)).then_some(player)
+            }
+        };
+        // the hand stays off until the player panels re-anchor it
+        if let Some(seat) = missed {
+            let event = self.turns.last().unwrap().events.last().unwrap();
+            let warning = format!(
+                "{} doesn't match the cards tracked for {}, so a message was missed",
+                event, self.seats[seat].name
+            );
+            self.warn_seat(seat, warning);
+        }
     }
 
     pub fn build_table(&self) -> Vec<Record> {
         // associate the player names with the table
         let table = self.card_tracker.table();
-        let mut result: Vec<Record> = Vec::new();
-        for (name, id) in &self.players {
-            result.push((
-                name.to_string(),
-                *self.colors.get(name).unwrap_or(&Color32::WHITE),
-                table[*id],
-            ));
-        }
-        result
+        let mut seats: Vec<_> = self.seats.iter().zip(table).collect();
+        // players missing from the turn order go last
+        seats.sort_by_key(|(seat, _)| {
+            self.turn_order
+                .iter()
+                .position(|n| *n == seat.id)
+                .unwrap_or(usize::MAX)
+        });
+        seats
+            .into_iter()
+            .map(|(seat, cards)| {
+                (
+                    seat.name.clone(),
+                    seat.color.unwrap_or(Color32::WHITE),
+                    cards,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{assert_same_table, log, replay, USERNAME};
+    use crate::format::fmt_player;
+    use colonizer_core::resource::Resource;
+    use scraper::{Html, Selector};
+
+    const RED: (u8, u8, u8) = (226, 113, 116);
+    const BLUE: (u8, u8, u8) = (34, 54, 151);
+    const SETTLEMENT: &str = r#"<img src="/dist/images/settlement_red.svg" alt="settlement">"#;
+
+    /// A log message in a seat's color, about a player with `avatar`
+    fn post((r, g, b): (u8, u8, u8), avatar: &str, body: &str) -> String {
+        format!(
+            r#"<div class="message_post" style="color: rgb({r}, {g}, {b});"><img src="/dist/images/icon_{avatar}.svg" alt="{avatar}">{body}</div>"#
+        )
+    }
+
+    fn card(name: &str) -> String {
+        format!(r#"<img src="/dist/images/card_{name}.svg" alt="{name}">"#)
+    }
+
+    #[test]
+    fn test_update_message() {
+        let html = log("game1");
+        let full = replay("game1");
+
+        // feed the messages one at a time, like the log observer does
+        let mut incremental = State::new(USERNAME.to_owned());
+        let document = Html::parse_document(&html);
+        for message in document.select(&Selector::parse(".message_post").unwrap()) {
+            incremental.update_message(&message.html());
+        }
+
+        assert_eq!(incremental.seen, full.seen);
+        assert_eq!(incremental.len(), full.len());
+        assert_same_table(&incremental, &full);
+    }
+
+    #[test]
+    fn test_turns() {
+        let state = replay("game1");
+
+        assert_eq!(
+            state.turn_order,
+            ["Magee#8353", "Kenric", "Meras", "Concha"]
+        );
+        let names: Vec<_> = state.build_table().into_iter().map(|r| r.0).collect();
+        assert_eq!(names, state.turn_order);
+
+        let setup = &state.turns()[0];
+        assert_eq!(setup.player, None);
+        let first = &state.turns()[1];
+        assert_eq!(first.player.as_deref(), Some("Magee#8353"));
+        assert_eq!(
+            first.events[0],
+            Event::Roll {
+                player: "Magee#8353".to_owned(),
+                dice: [1, 6],
+            }
+        );
+        // the players take turns in seating order
+        for (i, turn) in state.turns()[1..].iter().enumerate() {
+            assert_eq!(turn.player.as_ref(), Some(&state.turn_order[i % 4]));
+        }
+        assert_eq!(state.current_turn(), 61);
+        assert_eq!(
+            state.active_player(),
+            state.turns().last().unwrap().player.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_tricky_names() {
+        let log = [
+            post(
+                RED,
+                "player",
+                &format!("Wes built a fort placed a {SETTLEMENT}"),
+            ),
+            post(BLUE, "bot", &format!("Ëlla.ü-1 placed a {SETTLEMENT}")),
+            post(
+                RED,
+                "player",
+                &format!(
+                    "Wes built a fort received starting resources: {}{}{}{}",
+                    card("ore"),
+                    card("grain"),
+                    card("wool"),
+                    card("wool")
+                ),
+            ),
+            post(
+                BLUE,
+                "bot",
+                &format!("Ëlla.ü-1 received starting resources: {}", card("wool")),
+            ),
+            post(
+                RED,
+                "player",
+                &format!("Wes built a fort bought {}", card("devcardback")),
+            ),
+            post(
+                BLUE,
+                "bot",
+                &format!(
+                    "Ëlla.ü-1 stole {} from: Wes built a fort",
+                    card("rescardback")
+                ),
+            ),
+        ]
+        .concat();
+        let mut state = State::new(USERNAME.to_owned());
+        // the player panels show the exact names before the log is read
+        state.add_names(["Wes built a fort", "Ëlla.ü-1"]);
+        state.update(&log);
+
+        assert_eq!(state.turn_order, ["Wes built a fort", "Ëlla.ü-1"]);
+        assert_eq!(state.dev_cards[state.players["Wes built a fort"]], 1);
+        assert_eq!(state.len(), 1);
+        let table = state.build_table();
+        let totals: Vec<f64> = table
+            .iter()
+            .map(|(_, _, cards)| cards.iter().map(|&(_, expected, _)| expected).sum())
+            .collect();
+        assert_eq!(totals, [0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_seats() {
+        let log = [
+            post(RED, "player", &format!("Kim#1234 placed a {SETTLEMENT}")),
+            post(
+                RED,
+                "player",
+                &format!(
+                    "Kim#1234 received starting resources: {}{}{}",
+                    card("ore"),
+                    card("ore"),
+                    card("ore")
+                ),
+            ),
+            post(
+                BLUE,
+                "bot",
+                &format!("Lee received starting resources: {}", card("wool")),
+            ),
+            // a bot took over the red seat
+            post(RED, "bot", &format!("Kim discarded: {}", card("ore"))),
+            post(
+                BLUE,
+                "bot",
+                &format!("Lee stole {} from: Kim", card("rescardback")),
+            ),
+            post(
+                BLUE,
+                "bot",
+                &format!(
+                    "Lee traded: {} for: {} with: Kim#1234",
+                    card("wool"),
+                    card("ore")
+                ),
+            ),
+        ]
+        .concat();
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&log);
+
+        let red = &state.seats()[state.players["Kim"]];
+        assert_eq!(red.id, "Kim#1234");
+        assert_eq!(red.name, "Kim");
+        assert_eq!(red.icon.as_deref(), Some("bot"));
+        assert_eq!(
+            state.color("Kim#1234"),
+            Some(Color32::from_rgb(226, 113, 116))
+        );
+        // the events of both names went to the same seat
+        assert_eq!(state.turn_order, ["Kim#1234"]);
+        let table = state.build_table();
+        let names: Vec<&str> = table.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, ["Kim", "Lee"]);
+        assert_eq!(table[0].2[Resource::Wool as usize].0, 1);
+        assert_eq!(table[1].2[Resource::Ore as usize].0, 2);
+    }
+
+    #[test]
+    fn test_bot_takeover() {
+        let notice = |body: &str| {
+            format!(r#"<div class="message_post" style="color: rgb(102, 102, 102);">{body}</div>"#)
+        };
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(
+            &[
+                post(
+                    RED,
+                    "player",
+                    &format!(
+                        "Kim received starting resources: {}{}",
+                        card("ore"),
+                        card("ore")
+                    ),
+                ),
+                post(
+                    BLUE,
+                    "bot",
+                    &format!("Lee received starting resources: {}", card("wool")),
+                ),
+            ]
+            .concat(),
+        );
+
+        state.update_message(&notice("Kim has left the game"));
+        assert!(state.seat("Kim").unwrap().bot);
+        assert_eq!(fmt_player(&state, "Kim"), "Kim (bot)");
+        // a name nobody posted in a seat's color can't be told apart from a new player
+        state.update_message(&post(
+            BLUE,
+            "bot",
+            &format!("Lee stole {} from: Kim (bot)", card("rescardback")),
+        ));
+        assert_eq!(
+            state.warnings(),
+            ["Kim (bot) isn't at the table, so a message about them was skipped"]
+        );
+        // the bot goes by another name in the seat's color
+        state.update_message(&post(
+            RED,
+            "bot",
+            &format!("Kim (bot) discarded: {}", card("ore")),
+        ));
+        state.update_message(&post(
+            BLUE,
+            "bot",
+            &format!("Lee stole {} from: Kim (bot)", card("rescardback")),
+        ));
+        assert_eq!(state.seats().len(), 2);
+        assert_eq!(state.display_name("Kim"), "Kim (bot)");
+
+        state.update_message(&notice("Kim reconnected"));
+        let seat = state.seat("Kim").unwrap();
+        assert!(!seat.bot);
+        assert_eq!(seat.name, "Kim");
+        let table = state.build_table();
+        assert_eq!(table[0].2[Resource::Ore as usize].0, 0);
+        assert_eq!(table[1].2[Resource::Ore as usize].0, 1);
+
+        // a bot's avatar in the seat also means the player left
+        state.update_message(&post(RED, "player", &format!("Kim got: {}", card("ore"))));
+        state.update_message(&post(
+            RED,
+            "bot",
+            &format!("Kim discarded: {}", card("ore")),
+        ));
+        assert!(state.seat("Kim").unwrap().bot);
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&post(
+            RED,
+            "player",
+            &format!("Kim received starting resources: {}", card("ore")),
+        ));
+        let counts = |resources| {
+            [PlayerCounts {
+                name: "Kim".to_owned(),
+                resources,
+                dev_cards: 0,
+            }]
+        };
+        // the panel may show a card the log hasn't yet
+        state.update_counts(&counts(2));
+        assert!(state.warnings().is_empty());
+        assert_eq!(state.card_tracker.table()[0][Resource::Ore as usize].0, 1);
+
+        state.update_counts(&counts(2));
+        state.update_counts(&counts(2));
+        assert_eq!(
+            state.warnings(),
+            ["Kim has 2 resource cards, which the log can't explain"]
+        );
+        assert_eq!(
+            state.card_tracker.table()[0]
+                .map(|(_, e, _)| e)
+                .iter()
+                .sum::<f64>(),
+            2.0
+        );
+
+        // a later mismatch replaces the warning about the seat
+        state.update_counts(&counts(3));
+        state.update_counts(&counts(3));
+        assert_eq!(
+            state.warnings(),
+            ["Kim has 3 resource cards, which the log can't explain"]
+        );
+    }
+
+    #[test]
+    fn test_missed_message() {
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&post(
+            RED,
+            "player",
+            &format!("Kim received starting resources: {}", card("ore")),
+        ));
+        // the message that gave Kim two wool was missed
+        state.update_message(&post(
+            RED,
+            "player",
+            &format!("Kim discarded: {}", card("wool")),
+        ));
+        assert_eq!(state.warnings().len(), 1);
+        assert!(state.warnings()[0].ends_with("so a message was missed"));
+
+        // the panel re-anchors the hand
+        let counts = [PlayerCounts {
+            name: "Kim".to_owned(),
+            resources: 2,
+            dev_cards: 0,
+        }];
+        state.update_counts(&counts);
+        state.update_counts(&counts);
+        assert_eq!(
+            state.warnings(),
+            ["Kim has 2 resource cards, which the log can't explain"]
+        );
+        let table = state.card_tracker.table();
+        assert_eq!(table[0][Resource::Ore as usize].0, 1);
+        assert_eq!(table[0].map(|(_, e, _)| e).iter().sum::<f64>(), 2.0);
+    }
+
+    #[test]
+    fn test_own_hand() {
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&post(
+            RED,
+            "player",
+            &format!("Magee#8353 received starting resources: {}", card("ore")),
+        ));
+        let hand = |cards| OwnHand {
+            resources: Hand::from(cards),
+            dev_cards: 0,
+        };
+        state.update_own_hand(hand("wool"));
+        assert!(state.warnings().is_empty());
+
+        // hands that were likely misread
+        state.update_counts(&[PlayerCounts {
+            name: USERNAME.to_owned(),
+            resources: 1,
+            dev_cards: 0,
+        }]);
+        for misread in ["", "wool wool"] {
+            state.update_own_hand(hand(misread));
+            state.update_own_hand(hand(misread));
+        }
+        assert!(state.warnings().is_empty());
+        assert_eq!(state.card_tracker.table()[0][Resource::Ore as usize].0, 1);
+
+        state.update_own_hand(hand("wool"));
+        state.update_own_hand(hand("wool"));
+        assert_eq!(state.warnings().len(), 1);
+        assert_eq!(state.card_tracker.table()[0][Resource::Wool as usize].0, 1);
+        assert_eq!(state.card_tracker.table()[0][Resource::Ore as usize].0, 0);
+    }
+
+    #[test]
+    fn test_full_table() {
+        let mut state = State::new(USERNAME.to_owned());
+        let log: String = (0..=MAX_PLAYERS as u8)
+            .map(|i| post((i, 0, 0), "player", &format!("P{i} placed a {SETTLEMENT}")))
+            .collect();
+        state.update(&log);
+        assert_eq!(state.seats().len(), MAX_PLAYERS);
+        assert_eq!(
+            state.warnings(),
+            ["P6 isn't at the table, so a message about them was skipped"]
+        );
+    }
+
+    #[test]
+    fn test_spectator() {
+        for (game, username) in [("game2", USERNAME), ("game3", "fomoerotic")] {
+            let html = log(game);
+            let mut spectator = State::spectator();
+            spectator.update(&html);
+            let mut player = State::new(username.to_owned());
+            player.update(&html);
+
+            // the first rob by "you" gives away whose view the log is from
+            assert_eq!(spectator.perspective(), Some(username));
+            assert_same_table(&spectator, &player);
+        }
+
+        // the view changed if the player we thought it was robs "you"
+        let ore = card("ore");
+        let mut spectator = State::spectator();
+        spectator.update(
+            &[
+                post(
+                    RED,
+                    "bot",
+                    &format!("Brig received starting resources: {ore}"),
+                ),
+                post(
+                    BLUE,
+                    "bot",
+                    &format!("Dong received starting resources: {ore}"),
+                ),
+            ]
+            .concat(),
+        );
+        spectator.set_perspective(Some("Dong".to_owned()));
+        spectator.update_message(&post(BLUE, "bot", &format!("Dong stole: {ore} from you")));
+        assert_eq!(spectator.perspective(), None);
+        assert_eq!(
+            spectator.card_tracker.table()[spectator.players["Dong"]][Resource::Ore as usize].1,
+            2.0
+        );
+        // after a missed message nobody may hold the card
+        spectator.update_message(&post(BLUE, "bot", &format!("Dong stole: {ore} from you")));
+        assert_eq!(spectator.len(), 1);
+        assert_eq!(
+            spectator.warnings(),
+            ["Nobody Dong could have robbed held ore, so the rob was skipped"]
+        );
+    }
+
+    #[test]
+    fn test_rob_from_unknown() {
+        let ore = card("ore");
+        let mut spectator = State::spectator();
+        spectator.update(
+            &[
+                post(
+                    RED,
+                    "player",
+                    &format!("Brig received starting resources: {ore}"),
+                ),
+                post(
+                    BLUE,
+                    "bot",
+                    &format!("Dong received starting resources: {ore}"),
+                ),
+                post((0, 128, 0), "bot", &format!("Kim placed a {SETTLEMENT}")),
+                // the seat goes by two names now
+                post(RED, "bot", &format!("Brig (bot) placed a {SETTLEMENT}")),
+                post((0, 128, 0), "bot", &format!("Kim stole: {ore} from you")),
+            ]
+            .concat(),
+        );
+        assert_eq!(spectator.players["Brig (bot)"], spectator.players["Brig"]);
+        let table = spectator.card_tracker.table();
+        assert_eq!(
+            table[spectator.players["Brig"]][Resource::Ore as usize].1,
+            0.5
+        );
+        assert_eq!(
+            table[spectator.players["Dong"]][Resource::Ore as usize].1,
+            0.5
+        );
+    }
+
+    #[test]
+    fn test_new_game() {
+        let game1 = log("game1");
+        let game2 = log("game2");
+        let fresh = replay("game2");
+        let first = replay("game1");
+
+        // the next game's messages are appended to the same log
+        let mut state = State::new(USERNAME.to_owned());
+        let selector = Selector::parse(".message_post").unwrap();
+        for html in [&game1, &game2] {
+            for message in Html::parse_document(html).select(&selector) {
+                state.update_message(&message.html());
+            }
+        }
+        assert_eq!(state.build_table(), fresh.build_table());
+        assert_eq!(state.turns(), fresh.turns());
+        assert_eq!(
+            state.archive(),
+            [ArchivedGame {
+                winner: Some("Magee#8353".to_owned()),
+                table: first.build_table(),
+                turns: first.current_turn(),
+            }]
+        );
+
+        // or the log is replaced by the next game's, which starts out shorter
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&game1);
+        let start: String = Html::parse_document(&game2)
+            .select(&selector)
+            .take(20)
+            .map(|message| message.html())
+            .collect();
+        state.update(&start);
+        state.update(&game2);
+        assert_eq!(state.build_table(), fresh.build_table());
+        assert_eq!(state.archive().len(), 1);
+        assert_eq!(state.perspective(), Some("Magee#8353"));
+    }
+
+    #[test]
+    fn test_align() {
+        let seen: Vec<u64> = (0..20).collect();
+        assert_eq!(align(&[], &[1, 2]), Some(0));
+        assert_eq!(align(&seen, &(0..25).collect::<Vec<_>>()), Some(20));
+        // the start was trimmed
+        assert_eq!(align(&seen, &(5..25).collect::<Vec<_>>()), Some(15));
+        // a message was inserted
+        let mut log: Vec<u64> = (0..25).collect();
+        log.insert(3, 99);
+        assert_eq!(align(&seen, &log), Some(21));
+        // repeated messages are matched nearest to where they were
+        let seen = [1; 10];
+        assert_eq!(
+            align(&seen, &[[2; 3].as_slice(), &[1; 20]].concat()),
+            Some(11)
+        );
+        assert_eq!(align(&seen, &[4, 5, 6]), None);
+    }
+
+    #[test]
+    fn test_rewritten_log() {
+        let messages: Vec<String> = Html::parse_document(&log("game1"))
+            .select(&Selector::parse(".message_post").unwrap())
+            .map(|message| message.html())
+            .collect();
+        let mut expected = State::new(USERNAME.to_owned());
+        expected.update(&messages[..300].concat());
+
+        // the page dropped the oldest messages
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&messages[..200].concat());
+        state.update(&messages[50..300].concat());
+        assert_eq!(state.build_table(), expected.build_table());
+        assert_eq!(state.len(), expected.len());
+        assert!(state.warnings().is_empty());
+
+        // a message showed up among the ones already read
+        let notice =
+            r#"<div class="message_post" style="color: rgb(102, 102, 102);">Tile blocked</div>"#;
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(&messages[..200].concat());
+        let mut inserted = messages[..300].to_vec();
+        inserted.insert(100, notice.to_owned());
+        state.update(&inserted.concat());
+        assert_eq!(state.build_table(), expected.build_table());
+        assert_eq!(state.warnings().len(), 1);
+
+        // nothing lines up, so the log is read again
+        let mut state = State::new(USERNAME.to_owned());
+        state.update(
+            &Html::parse_document(&log("game3"))
+                .select(&Selector::parse(".message_post").unwrap())
+                .take(20)
+                .map(|message| message.html())
+                .collect::<String>(),
+        );
+        state.update(&messages[..300].concat());
+        assert_eq!(state.build_table(), expected.build_table());
+        assert_eq!(state.warnings().len(), 1);
+        assert!(state.archive().is_empty());
+    }
+
+    #[test]
+    fn test_log() {
+        let html = log("game1");
+        let state = replay("game1");
+        let log = state.log();
+        assert_eq!(log.len(), parse_messages(&html).len());
+        let last = log.last().unwrap();
+        assert_eq!(last.table, None);
+        assert_eq!(state.table_at(log.len() - 1), state.build_table());
+        assert_eq!(last.states, state.len());
+        assert_eq!(last.text, "trophy Magee#8353 won the game! trophy");
+
+        let placement = &log[3];
+        assert_eq!(placement.text, "Magee#8353 placed a settlement");
+        assert_eq!(
+            placement.event,
+            Some(Event::Placement {
+                player: "Magee#8353".to_owned(),
+                item: Item::Settlement
+            })
+        );
+        // moving the robber doesn't change any hands
+        assert!(log
+            .iter()
+            .any(|line| line.unparsed() && line.text.contains("moved robber")));
+        assert!(log
+            .iter()
+            .all(|line| !line.unparsed() || line.kind == Kind::Event));
     }
 }
@@ -0,0 +1,241 @@
+//! Follows a game in the browser and feeds it to the `State`
+use crate::api::Api;
+use crate::options::Options;
+use crate::recorder::Recorder;
+use crate::state::State;
+use cdp_client::protocol::runtime::{BindingCalled, Evaluate};
+use cdp_client::protocol::Notification;
+use cdp_client::{Browser, Error, Event, TargetFilter};
+use colonizer_core::html_parser::{parse_own_hand, parse_player_counts};
+use serde_json::Value;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+/// Matches the game tabs among Chrome's targets
+pub const GAME_URL: &str = "colonist.io";
+
+/// Name of the function the page calls with each new log message
+const LOG_BINDING: &str = "colonizerLogMessage";
+/// Installs a `MutationObserver` on the game log that passes each new message to
+/// `LOG_BINDING`. Returns the current log when the observer is installed, or
+/// `null` if the log isn't on the page or is already being observed.
+const OBSERVE_LOG: &str = r#"(() => {
+    const log = document.getElementById("game-log-text");
+    if (!log || log.dataset.colonizer) {
+        return null;
+    }
+    log.dataset.colonizer = "observed";
+    new MutationObserver(mutations => {
+        for (const mutation of mutations) {
+            for (const node of mutation.addedNodes) {
+                if (node.classList && node.classList.contains("message_post")) {
+                    colonizerLogMessage(node.outerHTML);
+                }
+            }
+        }
+    }).observe(log, { childList: true });
+    return log.innerHTML;
+})()"#;
+/// The whole page, which recordings keep a snapshot of
+const PAGE_HTML: &str = "document.documentElement.outerHTML";
+/// How often a recording takes a snapshot of the page
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(30);
+const PLAYER_PANELS: &str =
+    r#"Array.from(document.querySelectorAll(".player-card"), e => e.outerHTML).join("")"#;
+const OWN_HAND: &str = r#"(document.querySelector(".hand-cards") || {}).outerHTML || """#;
+
+pub struct Tracker {
+    pub state: State,
+    cdp: Browser,
+    /// New log messages pushed by the observer
+    log_messages: Receiver<Event>,
+    last_update: Instant,
+    /// How often to poll the page for the log, player panels and hand
+    poll_interval: Duration,
+    /// Saves the log messages as they arrive
+    recorder: Option<Recorder>,
+    /// When the recording last took a snapshot of the page
+    last_snapshot: Instant,
+    /// Serves the state to other programs
+    api: Option<Api>,
+}
+
+impl Tracker {
+    pub fn new(options: &Options) -> Self {
+        let filter = TargetFilter {
+            index: options.tab,
+            ..TargetFilter::page(GAME_URL)
+        };
+        let mut cdp = Browser::new(&options.debugger_url(), filter);
+        let log_messages = cdp.subscribe(BindingCalled::METHOD);
+        // this is replayed once the browser is reachable, and the first poll
+        // reports the error if it isn't
+        let _ = cdp.add_binding(LOG_BINDING);
+        let poll_interval = Duration::from_secs(1);
+        Self {
+            state: if options.spectate {
+                State::spectator()
+            } else {
+                State::new(options.username.clone())
+            },
+            cdp,
+            log_messages,
+            last_update: Instant::now() - poll_interval,
+            poll_interval,
+            recorder: None,
+            last_snapshot: Instant::now() - SNAPSHOT_INTERVAL,
+            api: None,
+        }
+    }
+
+    /// Records the log messages from now on
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Saves the recording, if any, before the program exits
+    pub fn finish(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.save() {
+                eprintln!("Unable to save the recording: {e}");
+            }
+        }
+    }
+
+    /// Serves the state after every poll from now on
+    pub fn set_api(&mut self, api: Api) {
+        self.api = Some(api);
+    }
+
+    /// The connection to the tab, e.g. to render into the page
+    pub fn browser(&mut self) -> &mut Browser {
+        &mut self.cdp
+    }
+
+    /// Cross-checks the state with the player panels and our hand on the page.
+    /// They're only a check, so failing to read them doesn't stop the log.
+    fn check_page(&mut self) {
+        if let Ok(panels) = self.cdp.evaluate(PLAYER_PANELS) {
+            let counts = parse_player_counts(&panels);
+            // the panels show the exact names, which helps finding them in the log
+            self.state
+                .add_names(counts.iter().map(|count| count.name.as_str()));
+            self.state.update_counts(&counts);
+        }
+        if let Some(hand) = self
+            .cdp
+            .evaluate(OWN_HAND)
+            .ok()
+            .and_then(|hand| parse_own_hand(&hand))
+        {
+            self.state.update_own_hand(hand);
+        }
+    }
+
+    /// Saves the page to the recording now and then, so the player panels and
+    /// our hand can be checked against the real page
+    fn snapshot_page(&mut self) -> Result<(), Error> {
+        let Some(recorder) = &mut self.recorder else {
+            return Ok(());
+        };
+        if self.last_snapshot.elapsed() < SNAPSHOT_INTERVAL {
+            return Ok(());
+        }
+        self.last_snapshot = Instant::now();
+        if let Ok(page) = self.cdp.evaluate(PAGE_HTML) {
+            recorder.record_page(&page)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the new log messages, and polls the page every second
+    pub fn poll(&mut self) -> Result<(), Error> {
+        let poll = self.last_update.elapsed() >= self.poll_interval;
+        if poll {
+            self.last_update = Instant::now();
+            // the observer only reports new messages, so read the existing ones when installing it
+            let log = self.cdp.evaluate_object(&Evaluate::new(OBSERVE_LOG))?;
+            if let Some(Value::String(html)) = log.value {
+                self.state.update(&html);
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.record_log(&html, &self.state)?;
+                }
+            }
+        }
+
+        if self.cdp.is_connected() {
+            self.cdp.poll()?;
+        }
+        for event in self.log_messages.try_iter() {
+            let Ok(call) = event.decode::<BindingCalled>() else {
+                continue;
+            };
+            if call.name != LOG_BINDING {
+                continue;
+            }
+            self.state.update_message(&call.payload);
+            if let Some(recorder) = &mut self.recorder {
+                recorder.record_message(&call.payload, &self.state)?;
+            }
+        }
+
+        // after the events, so the counts aren't compared with an older state
+        if poll {
+            self.check_page();
+            self.snapshot_page()?;
+        }
+        if let Some(api) = &mut self.api {
+            api.publish(&self.state);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{assert_same_table, log, replay, USERNAME};
+    use cdp_mock::MockBrowser;
+    use serde_json::json;
+
+    #[test]
+    fn test_follow_game() {
+        let mock = MockBrowser::start(&log("game1"));
+        mock.advance(10);
+        let mut installed = false;
+        mock.on_evaluate(move |expression, page| {
+            // the panels and the hand aren't on the page, which doesn't stop the log
+            if expression != OBSERVE_LOG {
+                page.throw("Cannot read properties of null");
+                return json!(null);
+            }
+            if installed {
+                return json!(null);
+            }
+            installed = true;
+            page.observe(LOG_BINDING);
+            json!(page.log_html())
+        });
+
+        let options = Options {
+            username: USERNAME.to_owned(),
+            debugger: mock.debugger(),
+            ..Options::default()
+        };
+        let mut tracker = Tracker::new(&options);
+        tracker.poll_interval = Duration::ZERO;
+        tracker.poll().unwrap();
+        while !mock.finished() {
+            mock.advance(25);
+            tracker.poll().unwrap();
+        }
+        // the last messages may still be on their way
+        for _ in 0..10 {
+            std::thread::sleep(Duration::from_millis(10));
+            tracker.poll().unwrap();
+        }
+
+        assert_same_table(&tracker.state, &replay("game1"));
+        assert!(tracker.state.warnings().is_empty());
+    }
+}
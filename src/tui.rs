@@ -0,0 +1,169 @@
+//! Shows the table in the terminal, for setups without a graphical session
+use crate::format::{ACTIVE_PLAYER, RESOURCE_COLUMNS};
+use crate::presenter::{Input, Presenter, View};
+use crate::tracker::Tracker;
+use cdp_client::Error;
+use egui::Color32;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Cell, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::time::Duration;
+
+/// The terminal, in raw mode on the alternate screen until dropped
+pub struct Tui {
+    terminal: DefaultTerminal,
+}
+
+impl Tui {
+    pub fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            terminal: ratatui::try_init()?,
+        })
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+impl Presenter for Tui {
+    fn present(&mut self, view: &View, _tracker: &mut Tracker) -> Result<Option<Input>, Error> {
+        self.terminal.draw(|frame| draw(frame, view))?;
+        Ok(None)
+    }
+
+    /// Closes on q, Esc or Ctrl+C, which raw mode keeps from interrupting us
+    fn closed(&mut self) -> bool {
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            let Ok(Event::Key(key)) = event::read() else {
+                continue;
+            };
+            let quit = match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => true,
+                KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+                _ => false,
+            };
+            if quit && key.kind == KeyEventKind::Press {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn color(color: Color32) -> Color {
+    Color::Rgb(color.r(), color.g(), color.b())
+}
+
+/// The same table as the window, with the turn and warnings below it
+fn draw(frame: &mut Frame, view: &View) {
+    let header = Row::new(
+        std::iter::once(Cell::from("Player").style(Style::new().fg(Color::LightBlue)))
+            .chain(
+                RESOURCE_COLUMNS
+                    .iter()
+                    .map(|&(name, c)| Cell::from(name).style(Style::new().fg(color(c)))),
+            )
+            .chain([Cell::from("Total")]),
+    );
+    let mut rows: Vec<Row> = view
+        .rows
+        .iter()
+        .map(|row| {
+            let mut name = Style::new().fg(color(row.color));
+            if row.active {
+                name = name.bg(color(ACTIVE_PLAYER));
+            }
+            Row::new(
+                std::iter::once(Cell::from(row.name.as_str()).style(name))
+                    .chain(row.cells.iter().map(|cell| {
+                        Cell::from(cell.text.as_str()).style(Style::new().fg(color(cell.color)))
+                    }))
+                    .chain([Cell::from(format!("{:>5.2}", row.total))]),
+            )
+        })
+        .collect();
+    rows.push(Row::new(
+        std::iter::once(Cell::from("Totals:"))
+            .chain(
+                view.totals
+                    .iter()
+                    .map(|total| Cell::from(format!("{total:>5.2}"))),
+            )
+            .chain([Cell::from(format!("{:>5}", view.states))]),
+    ));
+    let widths = std::iter::once(Constraint::Length(20))
+        .chain(RESOURCE_COLUMNS.map(|_| Constraint::Length(11)))
+        .chain([Constraint::Length(6)]);
+    let table = Table::new(rows, widths).header(header);
+
+    let mut lines = vec![Line::from(view.turn.as_str())];
+    lines.extend(view.perspective.as_deref().map(Line::from));
+    let red = Style::new().fg(Color::LightRed);
+    lines.extend(
+        view.error
+            .iter()
+            .chain(&view.warnings)
+            .map(|warning| Line::styled(warning.as_str(), red)),
+    );
+    lines.push(Line::styled("q to quit", Style::new().fg(Color::DarkGray)));
+
+    let [top, bottom] = Layout::vertical([
+        Constraint::Length(view.rows.len() as u16 + 2),
+        Constraint::Min(0),
+    ])
+    .areas(frame.area());
+    frame.render_widget(table, top);
+    frame.render_widget(Paragraph::new(lines), bottom);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::replay;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn test_draw() {
+        let state = replay("game1");
+        let view = View::new(&state, None);
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 12)).unwrap();
+        terminal.draw(|frame| draw(frame, &view)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let lines: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect();
+
+        assert!(lines[0].starts_with("Player"));
+        for (i, row) in view.rows.iter().enumerate() {
+            let line = &lines[i + 1];
+            assert!(line.starts_with(&row.name), "{line}");
+            // the cells are as fmt_resource formats them
+            let start = 21 + RESOURCE_COLUMNS.len() * 12;
+            let cells: String = row
+                .cells
+                .iter()
+                .map(|cell| format!("{} ", cell.text))
+                .collect();
+            assert_eq!(&line[21..start], cells);
+            // with the best bets highlighted
+            for (j, cell) in row.cells.iter().enumerate() {
+                let x = 21 + j as u16 * 12;
+                assert_eq!(buffer[(x, i as u16 + 1)].fg, color(cell.color));
+            }
+        }
+        assert!(lines[view.rows.len() + 1].starts_with("Totals:"));
+        assert!(lines[view.rows.len() + 2].starts_with(&view.turn));
+    }
+}